@@ -1,8 +1,6 @@
 use std::collections::HashMap;
 use std::iter::zip;
 
-use helpers;
-
 type Input = (Vec<u32>, Vec<u32>);
 
 fn part1((left_list, right_list): &Input) -> u32 {
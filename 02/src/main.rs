@@ -9,7 +9,7 @@ fn parse_input(input: String) -> Vec<Vec<i32>> {
         .collect()
 }
 
-fn check_report(report: &Vec<i32>) -> bool {
+fn check_report(report: &[i32]) -> bool {
     let mut all_positives = true;
     let mut all_negatives = true;
     let mut out_of_range = false;
@@ -32,18 +32,15 @@ fn check_report(report: &Vec<i32>) -> bool {
     !out_of_range && (all_positives || all_negatives)
 }
 
-fn part1(input: &Vec<Vec<i32>>) -> usize {
-    input
-        .into_iter()
-        .filter(|report| check_report(*report))
-        .count()
+fn part1(input: &[Vec<i32>]) -> usize {
+    input.iter().filter(|report| check_report(report)).count()
 }
 
-fn part2(input: &Vec<Vec<i32>>) -> usize {
+fn part2(input: &[Vec<i32>]) -> usize {
     input
-        .into_iter()
+        .iter()
         .filter(|report| {
-            if check_report(*report) {
+            if check_report(report) {
                 return true;
             } else {
                 for i in 0..report.len() {
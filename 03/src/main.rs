@@ -1,6 +1,6 @@
 use regex::Regex;
 
-fn part1(input: &String) -> i32 {
+fn part1(input: &str) -> i32 {
     let re = Regex::new(r"mul\((\d+),(\d+)\)").unwrap();
     re.captures_iter(input)
         .map(|captures| {
@@ -11,7 +11,7 @@ fn part1(input: &String) -> i32 {
         .sum()
 }
 
-fn part2(input: &String) -> i32 {
+fn part2(input: &str) -> i32 {
     let mut enabled = true;
     let mut result = 0;
 
@@ -1,122 +1,175 @@
+use std::collections::HashSet;
+
 fn parse_input(text: String) -> Vec<Vec<char>> {
     text.split("\n").map(|l| l.chars().collect()).collect()
 }
 
+const DIRECTIONS: [(&str, isize, isize); 8] = [
+    ("N", -1, 0),
+    ("NE", -1, 1),
+    ("E", 0, 1),
+    ("SE", 1, 1),
+    ("S", 1, 0),
+    ("SW", 1, -1),
+    ("W", 0, -1),
+    ("NW", -1, -1),
+];
+
+fn cell(input: &[Vec<char>], r: usize, c: usize, dr: isize, dc: isize) -> Option<char> {
+    let new_r = r.checked_add_signed(dr)?;
+    let new_c = c.checked_add_signed(dc)?;
+    input.get(new_r)?.get(new_c).copied()
+}
+
 fn search_word(
     input: &[Vec<char>],
     r: usize,
     c: usize,
-    word_to_find: &Vec<char>,
+    word_to_find: &[char],
     dr: isize,
     dc: isize,
 ) -> bool {
-    let max_r = input.len();
-    let max_c = input[0].len();
-
     word_to_find.iter().enumerate().all(|(i, curr_char)| {
-        let new_r = match r.checked_add_signed(i as isize * dr) {
-            Some(new_r) if new_r < max_r => new_r,
-            Some(_) => return false,
-            None => return false,
-        };
-
-        let new_c = match c.checked_add_signed(i as isize * dc) {
-            Some(new_c) if new_c < max_c => new_c,
-            Some(_) => return false,
-            None => return false,
-        };
-
-        input[new_r][new_c] == *curr_char
+        cell(input, r, c, i as isize * dr, i as isize * dc) == Some(*curr_char)
     })
 }
 
-fn search_with_deltas(
-    input: &[Vec<char>],
-    r: usize,
-    c: usize,
-    word_to_find: &Vec<char>,
-    dr: isize,
-    dc: isize,
-) -> bool {
-    search_word(input, r, c, word_to_find, dr, dc)
-        || search_word(
-            input,
-            r,
-            c,
-            &word_to_find.iter().copied().rev().collect(),
-            dr,
-            dc,
-        )
+#[derive(Debug, PartialEq)]
+struct Match<'a> {
+    word: &'a str,
+    start: (usize, usize),
+    direction: &'static str,
 }
 
-fn search_horizontally(input: &[Vec<char>], r: usize, c: usize, word_to_find: &Vec<char>) -> bool {
-    search_with_deltas(input, r, c, word_to_find, 0, 1)
-}
+// Each occurrence is reported once: a word that reads the same both ways (or
+// a single letter) keeps only the first direction it was found in.
+fn find_words<'a>(input: &[Vec<char>], words: &[&'a str]) -> Vec<Match<'a>> {
+    let words: Vec<(&str, Vec<char>)> = words
+        .iter()
+        .map(|&word| (word, word.chars().collect::<Vec<char>>()))
+        .filter(|(_, chars)| !chars.is_empty())
+        .collect();
+    let mut matches = vec![];
+    let mut occurrences = HashSet::new();
 
-fn search_vertically(input: &[Vec<char>], r: usize, c: usize, word_to_find: &Vec<char>) -> bool {
-    search_with_deltas(input, r, c, word_to_find, 1, 0)
+    for r in 0..input.len() {
+        for c in 0..input[r].len() {
+            for (word, chars) in &words {
+                for (direction, dr, dc) in DIRECTIONS {
+                    if !search_word(input, r, c, chars, dr, dc) {
+                        continue;
+                    }
+                    let length = chars.len() as isize - 1;
+                    let end = (
+                        r.saturating_add_signed(length * dr),
+                        c.saturating_add_signed(length * dc),
+                    );
+                    if occurrences.insert((*word, (r, c).min(end), (r, c).max(end))) {
+                        matches.push(Match {
+                            word,
+                            start: (r, c),
+                            direction,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    matches
 }
 
-fn search_diagonally_backwards(
-    input: &[Vec<char>],
-    r: usize,
-    c: usize,
-    word_to_find: &Vec<char>,
-) -> bool {
-    search_with_deltas(input, r, c, word_to_find, 1, -1)
+// A 2D mask of letters, as offsets from its top-left corner; '.' in the mask
+// text matches any letter.
+#[derive(Clone, PartialEq)]
+struct Pattern {
+    cells: Vec<(usize, usize, char)>,
 }
 
-fn search_diagonally_forwards(
-    input: &[Vec<char>],
-    r: usize,
-    c: usize,
-    word_to_find: &Vec<char>,
-) -> bool {
-    search_with_deltas(input, r, c, word_to_find, 1, 1)
+impl Pattern {
+    // Shifts the cells so the mask's bounding box starts at (0, 0), which
+    // keeps rotations comparable whatever blank rows or columns surround it.
+    fn normalised(mut cells: Vec<(usize, usize, char)>) -> Pattern {
+        let min_r = cells.iter().map(|&(r, _, _)| r).min().unwrap_or(0);
+        let min_c = cells.iter().map(|&(_, c, _)| c).min().unwrap_or(0);
+        for (r, c, _) in &mut cells {
+            *r -= min_r;
+            *c -= min_c;
+        }
+        cells.sort_unstable();
+        Pattern { cells }
+    }
+
+    fn parse(mask: &str) -> Pattern {
+        let cells: Vec<(usize, usize, char)> = mask
+            .lines()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, letter)| *letter != '.')
+                    .map(move |(c, letter)| (r, c, letter))
+            })
+            .collect();
+        Pattern::normalised(cells)
+    }
+
+    // The same mask turned a quarter clockwise.
+    fn rotated(&self) -> Pattern {
+        let height = self.cells.iter().map(|(r, _, _)| r + 1).max().unwrap_or(0);
+        let cells: Vec<(usize, usize, char)> = self
+            .cells
+            .iter()
+            .map(|&(r, c, letter)| (c, height - 1 - r, letter))
+            .collect();
+        Pattern::normalised(cells)
+    }
+
+    // The distinct rotations of the mask, so symmetric masks are only
+    // counted once per position.
+    fn rotations(&self) -> Vec<Pattern> {
+        let mut rotations = vec![self.clone()];
+        let mut current = self.rotated();
+        while !rotations.contains(&current) {
+            let next = current.rotated();
+            rotations.push(current);
+            current = next;
+        }
+        rotations
+    }
+
+    fn matches_at(&self, input: &[Vec<char>], r: usize, c: usize) -> bool {
+        self.cells
+            .iter()
+            .all(|&(dr, dc, letter)| cell(input, r + dr, c + dc, 0, 0) == Some(letter))
+    }
 }
 
-fn part1(input: &[Vec<char>]) -> isize {
-    let mut result = 0;
-    let word_to_find = "XMAS";
-    let chars: Vec<char> = word_to_find.chars().collect();
+// Top-left corners of every placement of the mask, in any rotation.
+fn find_pattern(input: &[Vec<char>], mask: &str) -> Vec<(usize, usize)> {
+    let rotations = Pattern::parse(mask).rotations();
+    let mut corners = vec![];
 
-    (0..input.len()).for_each(|r| {
-        (0..input[r].len()).for_each(|c| {
-            if search_horizontally(input, r, c, &chars) {
-                result += 1;
-            }
-            if search_vertically(input, r, c, &chars) {
-                result += 1;
-            }
-            if search_diagonally_forwards(input, r, c, &chars) {
-                result += 1;
-            }
-            if search_diagonally_backwards(input, r, c, &chars) {
-                result += 1;
-            }
-        });
-    });
+    for r in 0..input.len() {
+        for c in 0..input[r].len() {
+            corners.extend(
+                rotations
+                    .iter()
+                    .filter(|pattern| pattern.matches_at(input, r, c))
+                    .map(|_| (r, c)),
+            );
+        }
+    }
 
-    result
+    corners
 }
 
-fn part2(input: &[Vec<char>]) -> isize {
-    let mut result = 0;
-    let word_to_find = "MAS";
-    let chars = &word_to_find.chars().collect();
-    let word_len = word_to_find.len();
-
-    (0..input.len()).for_each(|r| {
-        (0..input[r].len()).for_each(|c| {
-            if search_diagonally_forwards(input, r, c, chars)
-                && search_diagonally_backwards(input, r, c + word_len - 1, chars)
-            {
-                result += 1
-            }
-        });
-    });
+fn part1(input: &[Vec<char>]) -> usize {
+    find_words(input, &["XMAS"]).len()
+}
 
-    result
+fn part2(input: &[Vec<char>]) -> usize {
+    find_pattern(input, "M.S\n.A.\nM.S").len()
 }
 
 fn main() {
@@ -127,8 +180,80 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 9);
 
+    let sample_matches = find_words(&sample_input, &["XMAS", "SAMX"]);
+    assert_eq!(sample_matches.len(), 36);
+    assert!(sample_matches.contains(&Match {
+        word: "XMAS",
+        start: (0, 4),
+        direction: "SE"
+    }));
+    let grid = parse_input(String::from("ABC\nDEF"));
+    assert_eq!(
+        find_words(&grid, &["FB", "AE"]),
+        vec![
+            Match {
+                word: "AE",
+                start: (0, 0),
+                direction: "SE"
+            },
+            Match {
+                word: "FB",
+                start: (1, 2),
+                direction: "NW"
+            }
+        ]
+    );
+    assert_eq!(Pattern::parse("A.\n.B").rotations().len(), 4);
+    assert_eq!(Pattern::parse("A.\n.A").rotations().len(), 2);
+    assert_eq!(Pattern::parse(".A.\nA.A\n.A.").rotations().len(), 1);
+    assert_eq!(find_pattern(&grid, "AB\nD"), vec![(0, 0)]);
+    assert_eq!(find_pattern(&grid, "CF\nB"), vec![(0, 1)]);
+    assert_eq!(Pattern::parse(".A").rotations().len(), 1);
+    assert_eq!(find_pattern(&grid, ".A"), vec![(0, 0)]);
+    assert_eq!(find_pattern(&grid, "..\n.B\n.E"), vec![(0, 1)]);
+    assert_eq!(find_words(&grid, &["E"]).len(), 1);
+    let palindromes = parse_input(String::from("ABA\nXBX"));
+    assert_eq!(
+        find_words(&palindromes, &["ABA", "BB"]),
+        vec![
+            Match {
+                word: "ABA",
+                start: (0, 0),
+                direction: "E"
+            },
+            Match {
+                word: "BB",
+                start: (0, 1),
+                direction: "S"
+            }
+        ]
+    );
+
     let text = helpers::input_file!();
     let input = parse_input(text);
+
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("words") => {
+            let words: Vec<&str> = args[2..].iter().map(String::as_str).collect();
+            for found in find_words(&input, &words) {
+                println!(
+                    "{} ({},{}) {}",
+                    found.word, found.start.0, found.start.1, found.direction
+                );
+            }
+            return;
+        }
+        // Mask rows are separated by '/', e.g. "M.S/.A./M.S".
+        Some("pattern") => {
+            let mask = args[2].replace('/', "\n");
+            for (r, c) in find_pattern(&input, &mask) {
+                println!("({r},{c})");
+            }
+            return;
+        }
+        _ => {}
+    }
     let answer1 = part1(&input);
     println!("{}", answer1);
     let answer2 = part2(&input);
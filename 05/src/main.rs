@@ -1,4 +1,3 @@
-use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 
 type Input1 = HashMap<u32, HashSet<u32>>;
@@ -7,18 +6,18 @@ type Input = (Input1, Input2);
 
 fn parse_page_ordering_rules(text: &str) -> Input1 {
     let mut rules = HashMap::new();
-    
+
     for rule in text.lines() {
         let parts: Vec<&str> = rule.split('|').collect();
         let before = parts[0].parse::<u32>().unwrap();
         let after = parts[1].parse::<u32>().unwrap();
-    
+
         rules
             .entry(before)
             .or_insert_with(HashSet::new)
             .insert(after);
     }
-    
+
     rules
 }
 
@@ -36,26 +35,63 @@ fn parse_input(text: &str) -> Input {
     (parse_page_ordering_rules(parts[0]), parse_updates(parts[1]))
 }
 
-fn get_ordering_rules(page_ordering_rules: &Input1, page: u32) -> HashSet<u32> {
-    match page_ordering_rules.get(&page) {
-        Some(rules) => rules.clone(),
-        None => HashSet::new(),
-    }
+// A rule `before|after` broken by an update, with the index of each page.
+#[derive(Debug, PartialEq)]
+struct Violation {
+    before: u32,
+    after: u32,
+    before_index: usize,
+    after_index: usize,
 }
 
-fn is_valid(update: &Vec<u32>, page_ordering_rules: &Input1) -> bool {
-    let mut following_pages: HashSet<u32> = update.iter().copied().collect::<HashSet<u32>>();
-    for page in update {
-        following_pages.remove(page);
-        if following_pages
-            .difference(&get_ordering_rules(page_ordering_rules, *page))
-            .count()
-            > 0
-        {
-            return false;
+fn violated_rules(update: &[u32], page_ordering_rules: &Input1) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    for (before_index, page) in update.iter().enumerate() {
+        let Some(following_pages) = page_ordering_rules.get(page) else {
+            continue;
+        };
+        for (after_index, earlier_page) in update[..before_index].iter().enumerate() {
+            if following_pages.contains(earlier_page) {
+                violations.push(Violation {
+                    before: *page,
+                    after: *earlier_page,
+                    before_index,
+                    after_index,
+                });
+            }
         }
     }
-    true
+
+    violations
+}
+
+// An update is valid when it breaks no rule: pages that no rule orders may come
+// in any order, unlike the original check which also required a rule for
+// every pair of pages.
+fn is_valid(update: &[u32], page_ordering_rules: &Input1) -> bool {
+    violated_rules(update, page_ordering_rules).is_empty()
+}
+
+fn validation_report(update: &[u32], page_ordering_rules: &Input1) -> String {
+    let violations = violated_rules(update, page_ordering_rules);
+    if violations.is_empty() {
+        return format!("{update:?}: valid");
+    }
+
+    let mut report = format!("{update:?}: invalid");
+    for violation in violations {
+        report.push_str(&format!(
+            "\n  rule {}|{} broken: {} at index {} comes after {} at index {}",
+            violation.before,
+            violation.after,
+            violation.before,
+            violation.before_index,
+            violation.after,
+            violation.after_index
+        ));
+    }
+    report
 }
 
 fn take_middle_value(vector: &[u32]) -> u32 {
@@ -70,24 +106,113 @@ fn part1((page_ordering_rules, updates): &Input) -> u32 {
         .sum()
 }
 
-fn correct_update(update: &[u32], page_ordering_rules: &Input1) -> Vec<u32> {
-    let mut new_update: Vec<u32> = update.to_vec().clone();
-    new_update.sort_by(|page1, page2| {
-        if get_ordering_rules(page_ordering_rules, *page1).contains(page2) {
-            Ordering::Less
-        } else {
-            Ordering::Greater
+#[derive(Debug, PartialEq)]
+enum OrderError {
+    // The pages of a cycle of rules, each one required before the next and the
+    // last one before the first.
+    Cycle(Vec<u32>),
+}
+
+impl std::fmt::Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OrderError::Cycle(pages) => {
+                let rules: Vec<String> = pages
+                    .iter()
+                    .zip(pages.iter().cycle().skip(1))
+                    .map(|(before, after)| format!("{before}|{after}"))
+                    .collect();
+                write!(f, "contradictory rules {}", rules.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Correction {
+    pages: Vec<u32>,
+    // False when the rules leave some pages free to swap, in which case the
+    // pages keep their relative order from the update.
+    unique: bool,
+}
+
+fn must_precede(page_ordering_rules: &Input1, before: u32, after: u32) -> bool {
+    page_ordering_rules
+        .get(&before)
+        .is_some_and(|pages| pages.contains(&after))
+}
+
+// Walks backwards along rules among the pages left over by the topological
+// sort: every one of them has a predecessor, so the walk must loop.
+fn find_cycle(remaining: &[u32], page_ordering_rules: &Input1) -> Vec<u32> {
+    let mut walk = vec![remaining[0]];
+    loop {
+        let current = walk[walk.len() - 1];
+        let previous = *remaining
+            .iter()
+            .find(|&&page| must_precede(page_ordering_rules, page, current))
+            .expect("Every remaining page has a predecessor");
+        if let Some(start) = walk.iter().position(|&page| page == previous) {
+            let mut cycle = walk[start..].to_vec();
+            cycle.reverse();
+            return cycle;
         }
-    });
-    new_update
+        walk.push(previous);
+    }
+}
+
+// Kahn's algorithm over the rules between the pages of the update, always
+// taking the earliest available page of the original update.
+fn correct_update(update: &[u32], page_ordering_rules: &Input1) -> Result<Correction, OrderError> {
+    let mut in_degrees: Vec<usize> = update
+        .iter()
+        .map(|&page| {
+            update
+                .iter()
+                .filter(|&&other| must_precede(page_ordering_rules, other, page))
+                .count()
+        })
+        .collect();
+    let mut placed = vec![false; update.len()];
+    let mut pages = Vec::with_capacity(update.len());
+    let mut unique = true;
+
+    while pages.len() < update.len() {
+        let available: Vec<usize> = (0..update.len())
+            .filter(|&i| !placed[i] && in_degrees[i] == 0)
+            .collect();
+        let Some(&next) = available.first() else {
+            let remaining: Vec<u32> = (0..update.len())
+                .filter(|&i| !placed[i])
+                .map(|i| update[i])
+                .collect();
+            return Err(OrderError::Cycle(find_cycle(
+                &remaining,
+                page_ordering_rules,
+            )));
+        };
+        unique &= available.len() == 1;
+
+        placed[next] = true;
+        pages.push(update[next]);
+        for (i, &page) in update.iter().enumerate() {
+            if must_precede(page_ordering_rules, update[next], page) {
+                in_degrees[i] -= 1;
+            }
+        }
+    }
+
+    Ok(Correction { pages, unique })
 }
 
 fn part2((page_ordering_rules, updates): &Input) -> u32 {
     updates
         .iter()
         .filter(|update| !is_valid(update, page_ordering_rules))
-        .map(|update| correct_update(update, page_ordering_rules))
-        .map(|update| take_middle_value(&update))
+        .map(|update| {
+            correct_update(update, page_ordering_rules).unwrap_or_else(|error| panic!("{error}"))
+        })
+        .map(|correction| take_middle_value(&correction.pages))
         .sum()
 }
 
@@ -99,8 +224,69 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 123);
 
+    let (sample_rules, sample_updates) = &sample_input;
+    assert!(sample_updates.iter().all(
+        |update| correct_update(update, sample_rules).is_ok_and(|correction| correction.unique)
+    ));
+    let partial_rules = parse_page_ordering_rules("1|3");
+    assert_eq!(
+        correct_update(&[2, 3, 1], &partial_rules),
+        Ok(Correction {
+            pages: vec![2, 1, 3],
+            unique: false
+        })
+    );
+    let cyclic_rules = parse_page_ordering_rules("1|2\n2|3\n3|1\n3|4");
+    let cycle = correct_update(&[4, 3, 2, 1], &cyclic_rules).unwrap_err();
+    assert_eq!(cycle, OrderError::Cycle(vec![1, 2, 3]));
+    assert_eq!(cycle.to_string(), "contradictory rules 1|2, 2|3, 3|1");
+
+    assert!(violated_rules(&sample_updates[0], sample_rules).is_empty());
+    assert!(is_valid(&[3, 1, 2], &parse_page_ordering_rules("1|2")));
+    assert!(!is_valid(&[2, 3, 1], &parse_page_ordering_rules("1|2")));
+    assert_eq!(
+        violated_rules(&sample_updates[3], sample_rules),
+        vec![Violation {
+            before: 97,
+            after: 75,
+            before_index: 1,
+            after_index: 0
+        }]
+    );
+    assert_eq!(
+        validation_report(&sample_updates[5], sample_rules),
+        concat!(
+            "[97, 13, 75, 29, 47]: invalid\n",
+            "  rule 75|13 broken: 75 at index 2 comes after 13 at index 1\n",
+            "  rule 29|13 broken: 29 at index 3 comes after 13 at index 1\n",
+            "  rule 47|13 broken: 47 at index 4 comes after 13 at index 1\n",
+            "  rule 47|29 broken: 47 at index 4 comes after 29 at index 3"
+        )
+    );
+
     let text = helpers::input_file!();
     let input = parse_input(&text);
+
+    if std::env::args().nth(1).as_deref() == Some("validate") {
+        let (page_ordering_rules, updates) = &input;
+        for update in updates {
+            println!("{}", validation_report(update, page_ordering_rules));
+        }
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("corrections") {
+        let (page_ordering_rules, updates) = &input;
+        for update in updates {
+            match correct_update(update, page_ordering_rules) {
+                Ok(Correction { pages, unique }) => println!(
+                    "{update:?} -> {pages:?}{}",
+                    if unique { "" } else { " (not unique)" }
+                ),
+                Err(error) => println!("{update:?}: {error}"),
+            }
+        }
+        return;
+    }
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
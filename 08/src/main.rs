@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use helpers::position::Position;
 
@@ -26,66 +26,232 @@ fn parse_input(text: String) -> Input {
     (frequencies, num_rows + 1, num_cols + 1)
 }
 
+// Which points on the line through two antennas of the same frequency are
+// antinodes. Points are A + t * (B - A) for rational t, and only those that
+// land exactly on the grid count.
+#[derive(Copy, Clone)]
+enum AntinodeRule {
+    // One point beyond each antenna, twice as far from the other (t = -1, 2).
+    Echo,
+    // Every integer multiple of the delta, the antennas included; with a
+    // maximum order, at most that many steps beyond each antenna.
+    Harmonics { max_order: Option<u32> },
+    // Every grid point on the line through the antennas, stepping by the
+    // gcd-reduced delta so that points between multiples are not skipped;
+    // the maximum order counts reduced steps.
+    Line { max_order: Option<u32> },
+    // The points splitting the segment into the given number of equal parts,
+    // e.g. the midpoint for 2 or the thirds for 3.
+    Divisions(u32),
+    // The points (between or beyond the antennas) whose distance from one
+    // antenna is p/q times their distance from the other.
+    DistanceRatio(u32, u32),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Source {
+    frequency: char,
+    antennas: (Position, Position),
+}
+
+type Antinodes = HashMap<Position, Vec<Source>>;
+
+fn point_at(first: Position, delta: Position, num: i32, den: i32) -> Option<Position> {
+    let (row, col) = (delta.get_row() * num, delta.get_col() * num);
+    if row % den != 0 || col % den != 0 {
+        return None;
+    }
+    Some(first + Position::new(row / den, col / den))
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The points first + k * step in bounds, in order along the line, with at most
+// `max_order` steps beyond either antenna. `step` must divide the delta.
+fn line_points(
+    first: Position,
+    second: Position,
+    step: Position,
+    max_order: Option<u32>,
+    num_rows: usize,
+    num_cols: usize,
+) -> Vec<Position> {
+    let within_order = |steps: u32| max_order.is_none_or(|order| steps <= order);
+    let mut points = vec![];
+
+    let mut point = first - step;
+    let mut steps = 1;
+    while within_order(steps) && point.is_within_bounds(num_rows, num_cols) {
+        points.push(point);
+        point -= step;
+        steps += 1;
+    }
+    points.reverse();
+
+    point = first;
+    while point != second {
+        points.push(point);
+        point += step;
+    }
+
+    steps = 0;
+    while within_order(steps) && point.is_within_bounds(num_rows, num_cols) {
+        points.push(point);
+        point += step;
+        steps += 1;
+    }
+
+    points
+}
+
+fn rule_points(
+    rule: AntinodeRule,
+    first: Position,
+    second: Position,
+    num_rows: usize,
+    num_cols: usize,
+) -> Vec<Position> {
+    let delta = second - first;
+    let fractions: Vec<(i32, i32)> = match rule {
+        AntinodeRule::Echo => vec![(-1, 1), (2, 1)],
+        AntinodeRule::Harmonics { max_order } => {
+            return line_points(first, second, delta, max_order, num_rows, num_cols);
+        }
+        AntinodeRule::Line { max_order } => {
+            let divisor = gcd(delta.get_row(), delta.get_col());
+            let step = Position::new(delta.get_row() / divisor, delta.get_col() / divisor);
+            return line_points(first, second, step, max_order, num_rows, num_cols);
+        }
+        AntinodeRule::Divisions(parts) => {
+            let parts = parts as i32;
+            (1..parts).map(|k| (k, parts)).collect()
+        }
+        AntinodeRule::DistanceRatio(p, q) => {
+            let (p, q) = (p as i32, q as i32);
+            if p == q {
+                vec![(1, 2)]
+            } else {
+                vec![(p, p + q), (q, p + q), (p, p - q), (q, q - p)]
+            }
+        }
+    };
+
+    fractions
+        .into_iter()
+        .filter_map(|(num, den)| point_at(first, delta, num, den))
+        .filter(|point| point.is_within_bounds(num_rows, num_cols))
+        .collect()
+}
+
 fn find_antinodes(
+    frequency: char,
     positions: &[Position],
     num_rows: usize,
     num_cols: usize,
-    consider_harmonics: bool,
-) -> HashSet<Position> {
-    let mut antinodes: HashSet<Position> = HashSet::new();
+    rule: AntinodeRule,
+) -> Antinodes {
+    let mut antinodes: Antinodes = HashMap::new();
 
     for i in 0..positions.len() {
         for j in i + 1..positions.len() {
-            let first = positions[i];
-            let second = positions[j];
-
-            if consider_harmonics {
-                antinodes.insert(first);
-                antinodes.insert(second);
+            let source = Source {
+                frequency,
+                antennas: (positions[i], positions[j]),
+            };
+            for point in rule_points(rule, positions[i], positions[j], num_rows, num_cols) {
+                let sources = antinodes.entry(point).or_default();
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
             }
+        }
+    }
 
-            let delta = Position::new(
-                second.get_row() - first.get_row(),
-                second.get_col() - first.get_col(),
-            );
+    antinodes
+}
 
-            let mut new_position = first - delta;
-            while new_position.is_within_bounds(num_rows, num_cols) {
-                antinodes.insert(new_position);
-                if !consider_harmonics {
-                    break;
-                }
-                new_position -= delta;
-            }
+fn all_antinodes((frequencies, num_rows, num_cols): &Input, rule: AntinodeRule) -> Antinodes {
+    let mut antinodes: Antinodes = HashMap::new();
 
-            new_position = second + delta;
-            while new_position.is_within_bounds(num_rows, num_cols) {
-                antinodes.insert(new_position);
-                if !consider_harmonics {
-                    break;
-                }
-                new_position += delta;
-            }
+    for (&frequency, positions) in frequencies {
+        for (point, sources) in find_antinodes(frequency, positions, *num_rows, *num_cols, rule) {
+            antinodes.entry(point).or_default().extend(sources);
         }
     }
+    for sources in antinodes.values_mut() {
+        sources.sort_unstable_by_key(|source| {
+            let (first, second) = source.antennas;
+            (
+                source.frequency,
+                (first.get_row(), first.get_col()),
+                (second.get_row(), second.get_col()),
+            )
+        });
+    }
 
     antinodes
 }
 
-fn part1((frequencies, num_rows, num_cols): &Input) -> usize {
-    frequencies
-        .values()
-        .flat_map(|positions| find_antinodes(positions, *num_rows, *num_cols, false))
-        .collect::<HashSet<Position>>()
-        .len()
+fn part1(input: &Input) -> usize {
+    all_antinodes(input, AntinodeRule::Echo).len()
+}
+
+fn part2(input: &Input) -> usize {
+    all_antinodes(input, AntinodeRule::Harmonics { max_order: None }).len()
 }
 
-fn part2((frequencies, num_rows, num_cols): &Input) -> usize {
-    frequencies
-        .values()
-        .flat_map(|positions| find_antinodes(positions, *num_rows, *num_cols, true))
-        .collect::<HashSet<Position>>()
-        .len()
+fn format_position(position: Position) -> String {
+    format!("({},{})", position.get_row(), position.get_col())
+}
+
+// The map as in the puzzle's illustrations: antennas keep their frequency and
+// the other antinodes are marked with '#'.
+fn render_map((frequencies, num_rows, num_cols): &Input, antinodes: &Antinodes) -> String {
+    let mut grid = vec![vec!['.'; *num_cols]; *num_rows];
+    for position in antinodes.keys() {
+        grid[position.get_urow()][position.get_ucol()] = '#';
+    }
+    for (&frequency, positions) in frequencies {
+        for position in positions {
+            grid[position.get_urow()][position.get_ucol()] = frequency;
+        }
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().collect::<String>() + "\n")
+        .collect()
+}
+
+fn explain_overlaps(antinodes: &Antinodes) -> Vec<String> {
+    let mut overlaps: Vec<(&Position, &Vec<Source>)> = antinodes
+        .iter()
+        .filter(|(_, sources)| sources.len() > 1)
+        .collect();
+    overlaps.sort_unstable_by_key(|(position, _)| (position.get_row(), position.get_col()));
+
+    overlaps
+        .into_iter()
+        .map(|(&position, sources)| {
+            let sources: Vec<String> = sources
+                .iter()
+                .map(|source| {
+                    format!(
+                        "{} {}-{}",
+                        source.frequency,
+                        format_position(source.antennas.0),
+                        format_position(source.antennas.1)
+                    )
+                })
+                .collect();
+            format!("{}: {}", format_position(position), sources.join(", "))
+        })
+        .collect()
 }
 
 fn main() {
@@ -95,7 +261,122 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 34);
 
+    let sample_overlaps = explain_overlaps(&all_antinodes(&sample_input, AntinodeRule::Echo));
+    assert_eq!(sample_overlaps, vec!["(1,3): 0 (2,5)-(3,7), A (5,6)-(9,9)"]);
+    let echoes = all_antinodes(
+        &sample_input,
+        AntinodeRule::Harmonics { max_order: Some(1) },
+    );
+    assert!(all_antinodes(&sample_input, AntinodeRule::Echo)
+        .keys()
+        .all(|position| echoes.contains_key(position)));
+
+    let pair = (Position::new(0, 0), Position::new(2, 4));
+    assert_eq!(
+        rule_points(AntinodeRule::Divisions(2), pair.0, pair.1, 10, 10),
+        vec![Position::new(1, 2)]
+    );
+    assert!(rule_points(AntinodeRule::Divisions(3), pair.0, pair.1, 10, 10).is_empty());
+    assert_eq!(
+        rule_points(AntinodeRule::DistanceRatio(1, 1), pair.0, pair.1, 10, 10),
+        vec![Position::new(1, 2)]
+    );
+    assert_eq!(
+        rule_points(
+            AntinodeRule::Harmonics { max_order: None },
+            pair.0,
+            pair.1,
+            5,
+            5
+        ),
+        vec![pair.0, pair.1]
+    );
+    assert_eq!(
+        rule_points(AntinodeRule::Line { max_order: None }, pair.0, pair.1, 5, 5),
+        vec![pair.0, Position::new(1, 2), pair.1]
+    );
+    assert_eq!(
+        rule_points(
+            AntinodeRule::Line { max_order: Some(1) },
+            Position::new(2, 2),
+            Position::new(4, 4),
+            10,
+            10
+        ),
+        (1..=5).map(|i| Position::new(i, i)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        all_antinodes(&sample_input, AntinodeRule::Line { max_order: None }).len(),
+        sample_answer2
+    );
+    assert_eq!(
+        render_map(
+            &sample_input,
+            &all_antinodes(&sample_input, AntinodeRule::Echo)
+        ),
+        concat!(
+            "......#....#\n",
+            "...#....0...\n",
+            "....#0....#.\n",
+            "..#....0....\n",
+            "....0....#..\n",
+            ".#....A.....\n",
+            "...#........\n",
+            "#......#....\n",
+            "........A...\n",
+            ".........A..\n",
+            "..........#.\n",
+            "..........#.\n",
+        )
+    );
+
+    let thirds = (Position::new(0, 0), Position::new(3, 3));
+    assert_eq!(
+        rule_points(
+            AntinodeRule::DistanceRatio(2, 1),
+            thirds.0,
+            thirds.1,
+            10,
+            10
+        ),
+        vec![
+            Position::new(2, 2),
+            Position::new(1, 1),
+            Position::new(6, 6)
+        ]
+    );
+    assert_eq!(
+        rule_points(
+            AntinodeRule::Harmonics { max_order: Some(0) },
+            thirds.0,
+            thirds.1,
+            10,
+            10
+        ),
+        vec![thirds.0, thirds.1]
+    );
+
     let input = parse_input(helpers::input_file!());
+
+    let rule = match std::env::args().nth(2).as_deref() {
+        Some("harmonics") => AntinodeRule::Harmonics { max_order: None },
+        Some("line") => AntinodeRule::Line { max_order: None },
+        _ => AntinodeRule::Echo,
+    };
+    match std::env::args().nth(1).as_deref() {
+        Some("overlaps") => {
+            for line in explain_overlaps(&all_antinodes(&input, rule)) {
+                println!("{line}");
+            }
+            return;
+        }
+        Some("map") => {
+            print!("{}", render_map(&input, &all_antinodes(&input, rule)));
+            return;
+        }
+        _ => {}
+    }
+
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
@@ -1,141 +1,322 @@
 use helpers::position::Position;
-use std::collections::HashSet;
+use std::fmt::Write;
 
-type Map = Vec<Vec<u32>>;
-type ScoresMap = Vec<Vec<HashSet<Position>>>;
-type RatingsMap = Vec<Vec<u32>>;
-type Input = (Map, HashSet<Position>, HashSet<Position>);
+struct Grid {
+    heights: Vec<Option<u32>>,
+    rows: usize,
+    cols: usize,
+}
 
-fn parse_input(input: &str) -> Input {
-    let mut starts = HashSet::new();
-    let mut ends = HashSet::new();
-    let map = input
-        .lines()
-        .enumerate()
-        .map(|(r, row)| {
-            row.chars()
-                .enumerate()
-                .map(|(c, digit)| {
-                    let height = digit.to_digit(10).unwrap();
-                    if height == 0 {
-                        starts.insert(Position::from_usize(r, c));
-                    }
-                    if height == 9 {
-                        ends.insert(Position::from_usize(r, c));
-                    }
-                    height
-                })
-                .collect()
-        })
-        .collect();
+impl Grid {
+    fn index(&self, position: Position) -> usize {
+        position.get_urow() * self.cols + position.get_ucol()
+    }
+
+    fn position(&self, index: usize) -> Position {
+        Position::from_usize(index / self.cols, index % self.cols)
+    }
 
-    (map, starts, ends)
+    // None for impassable cells and for positions outside the map.
+    fn height(&self, position: Position) -> Option<u32> {
+        if position.is_within_bounds(self.rows, self.cols) {
+            self.heights[self.index(position)]
+        } else {
+            None
+        }
+    }
 }
 
-fn calculate_scores(map: &Map, ends: &HashSet<Position>) -> ScoresMap {
-    let mut scores: ScoresMap = map
-        .iter()
-        .map(|row| row.iter().map(|_| HashSet::<Position>::new()).collect())
-        .collect();
+#[derive(Copy, Clone)]
+enum StepRule {
+    Exact(u32),
+    AtMost(u32),
+}
 
-    for end in ends {
-        scores[end.get_urow()][end.get_ucol()].insert(*end);
-    }
-
-    let mut current_height = 8;
-    loop {
-        for (r, row) in map.iter().enumerate() {
-            for (c, cell) in row.iter().enumerate() {
-                if *cell != current_height {
-                    continue;
-                }
-                let current_position = Position::from_usize(r, c);
-                for delta in [
-                    Position::new(-1, 0),
-                    Position::new(1, 0),
-                    Position::new(0, -1),
-                    Position::new(0, 1),
-                ] {
-                    let neighbour = current_position + delta;
-                    if neighbour.is_within_bounds(map.len(), map[0].len())
-                        && map[neighbour.get_urow()][neighbour.get_ucol()] == current_height + 1
-                    {
-                        scores[r][c] = scores[r][c]
-                            .union(&scores[neighbour.get_urow()][neighbour.get_ucol()].clone())
-                            .copied()
-                            .collect();
-                    }
-                }
-            }
+impl StepRule {
+    // Trails always climb, which keeps them acyclic whatever the rule.
+    fn allows(self, from: u32, to: u32) -> bool {
+        match self {
+            StepRule::Exact(step) => to == from + step,
+            StepRule::AtMost(step) => to > from && to - from <= step,
         }
-        match current_height.checked_sub(1) {
-            Some(next_height) => current_height = next_height,
-            None => break,
+    }
+}
+
+struct TrailRules {
+    start_height: u32,
+    end_height: u32,
+    step: StepRule,
+}
+
+impl Default for TrailRules {
+    fn default() -> Self {
+        TrailRules {
+            start_height: 0,
+            end_height: 9,
+            step: StepRule::Exact(1),
         }
     }
+}
+
+type Input = Grid;
+type RatingsMap = Vec<u64>;
 
-    scores
+struct ScoresMap {
+    summits: Vec<Position>,
+    words: usize,
+    reachable: Vec<u64>,
 }
 
-fn part1((map, starts, ends): &Input) -> usize {
-    let scores = calculate_scores(map, ends);
+impl ScoresMap {
+    fn bits(&self, index: usize) -> &[u64] {
+        &self.reachable[index * self.words..(index + 1) * self.words]
+    }
 
-    starts
-        .iter()
-        .map(|start| scores[start.get_urow()][start.get_ucol()].len())
-        .sum()
+    fn score(&self, index: usize) -> usize {
+        self.bits(index)
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn summits_from(&self, index: usize) -> Vec<Position> {
+        self.summits
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| self.bits(index)[bit / 64] & (1 << (bit % 64)) != 0)
+            .map(|(_, &summit)| summit)
+            .collect()
+    }
 }
 
-fn calculate_ratings(map: &Map, ends: &HashSet<Position>) -> RatingsMap {
-    let mut ratings: Map = map
+fn parse_input(input: &str) -> Input {
+    let lines: Vec<&str> = input.lines().collect();
+    let cols = lines.first().map_or(0, |line| line.len());
+    let heights = lines
         .iter()
-        .map(|row| row.iter().map(|_| 0).collect())
+        .flat_map(|line| {
+            assert_eq!(line.len(), cols, "Rows must all have the same length");
+            line.chars().map(|cell| match cell {
+                '.' => None,
+                digit => Some(digit.to_digit(10).expect("Invalid height")),
+            })
+        })
         .collect();
-    for end in ends {
-        ratings[end.get_urow()][end.get_ucol()] = 1;
-    }
-
-    let mut current_height = 8;
-    loop {
-        for (r, row) in map.iter().enumerate() {
-            for (c, cell) in row.iter().enumerate() {
-                if *cell != current_height {
-                    continue;
-                }
-                let current_position = Position::from_usize(r, c);
-                for delta in [
-                    Position::new(-1, 0),
-                    Position::new(1, 0),
-                    Position::new(0, -1),
-                    Position::new(0, 1),
-                ] {
-                    let neighbour = current_position + delta;
-                    if neighbour.is_within_bounds(map.len(), map[0].len())
-                        && map[neighbour.get_urow()][neighbour.get_ucol()] == current_height + 1
-                    {
-                        ratings[r][c] += ratings[neighbour.get_urow()][neighbour.get_ucol()];
-                    }
-                }
+
+    Grid {
+        heights,
+        rows: lines.len(),
+        cols,
+    }
+}
+
+fn uphill_neighbours(grid: &Grid, rules: &TrailRules, position: Position) -> Vec<Position> {
+    let Some(height) = grid.height(position) else {
+        return Vec::new();
+    };
+
+    [
+        position.up(),
+        position.left(),
+        position.right(),
+        position.down(),
+    ]
+    .into_iter()
+    .filter(|&neighbour| {
+        grid.height(neighbour).is_some_and(|next_height| {
+            next_height <= rules.end_height && rules.step.allows(height, next_height)
+        })
+    })
+    .collect()
+}
+
+fn positions_at_height(grid: &Grid, height: u32) -> Vec<Position> {
+    (0..grid.heights.len())
+        .filter(|&index| grid.heights[index] == Some(height))
+        .map(|index| grid.position(index))
+        .collect()
+}
+
+// Cell indices bucket-sorted from the end height down to the start height, so
+// every uphill neighbour is processed before the cells below it.
+fn descending_order(grid: &Grid, rules: &TrailRules) -> Vec<usize> {
+    assert!(
+        rules.start_height <= rules.end_height,
+        "Trails cannot climb from height {} down to {}",
+        rules.start_height,
+        rules.end_height
+    );
+    let mut buckets = vec![Vec::new(); (rules.end_height - rules.start_height + 1) as usize];
+    for (index, height) in grid.heights.iter().enumerate() {
+        if let Some(height) = height {
+            if (rules.start_height..=rules.end_height).contains(height) {
+                buckets[(rules.end_height - height) as usize].push(index);
             }
         }
-        match current_height.checked_sub(1) {
-            Some(next_height) => current_height = next_height,
-            None => break,
+    }
+    buckets.into_iter().flatten().collect()
+}
+
+fn calculate_scores(grid: &Grid, rules: &TrailRules) -> ScoresMap {
+    let summits = positions_at_height(grid, rules.end_height);
+    let words = summits.len().div_ceil(64);
+    let mut reachable = vec![0; grid.heights.len() * words];
+
+    for (bit, &summit) in summits.iter().enumerate() {
+        reachable[grid.index(summit) * words + bit / 64] |= 1 << (bit % 64);
+    }
+
+    for index in descending_order(grid, rules) {
+        for neighbour in uphill_neighbours(grid, rules, grid.position(index)) {
+            let neighbour = grid.index(neighbour);
+            for word in 0..words {
+                reachable[index * words + word] |= reachable[neighbour * words + word];
+            }
+        }
+    }
+
+    ScoresMap {
+        summits,
+        words,
+        reachable,
+    }
+}
+
+fn calculate_ratings(grid: &Grid, rules: &TrailRules) -> RatingsMap {
+    let mut ratings = vec![0; grid.heights.len()];
+    for summit in positions_at_height(grid, rules.end_height) {
+        ratings[grid.index(summit)] = 1;
+    }
+
+    for index in descending_order(grid, rules) {
+        for neighbour in uphill_neighbours(grid, rules, grid.position(index)) {
+            ratings[index] += ratings[grid.index(neighbour)];
         }
     }
 
     ratings
 }
 
-fn part2((map, starts, ends): &Input) -> u32 {
-    let ratings = calculate_ratings(map, ends);
+fn total_score(grid: &Grid, rules: &TrailRules) -> usize {
+    let scores = calculate_scores(grid, rules);
 
-    starts
-        .iter()
-        .map(|start| ratings[start.get_urow()][start.get_ucol()])
+    positions_at_height(grid, rules.start_height)
+        .into_iter()
+        .map(|start| scores.score(grid.index(start)))
         .sum()
 }
 
+fn total_rating(grid: &Grid, rules: &TrailRules) -> u64 {
+    let ratings = calculate_ratings(grid, rules);
+
+    positions_at_height(grid, rules.start_height)
+        .into_iter()
+        .map(|start| ratings[grid.index(start)])
+        .sum()
+}
+
+fn part1(grid: &Input) -> usize {
+    total_score(grid, &TrailRules::default())
+}
+
+fn part2(grid: &Input) -> u64 {
+    total_rating(grid, &TrailRules::default())
+}
+
+fn trailhead_summits(grid: &Grid, rules: &TrailRules) -> Vec<(Position, Vec<Position>)> {
+    let scores = calculate_scores(grid, rules);
+
+    positions_at_height(grid, rules.start_height)
+        .into_iter()
+        .map(|start| (start, scores.summits_from(grid.index(start))))
+        .collect()
+}
+
+struct Trails<'a> {
+    grid: &'a Grid,
+    rules: &'a TrailRules,
+    trail: Vec<Position>,
+    stack: Vec<(Vec<Position>, usize)>,
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<Position>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Each stack frame holds the positions that can extend the current
+        // trail; the bottom frame holds the trailheads.
+        while let Some((candidates, index)) = self.stack.last_mut() {
+            if *index >= candidates.len() {
+                self.stack.pop();
+                self.trail.pop();
+                continue;
+            }
+
+            let position = candidates[*index];
+            *index += 1;
+
+            if self.grid.height(position) == Some(self.rules.end_height) {
+                let mut trail = self.trail.clone();
+                trail.push(position);
+                return Some(trail);
+            }
+
+            self.trail.push(position);
+            self.stack
+                .push((uphill_neighbours(self.grid, self.rules, position), 0));
+        }
+
+        None
+    }
+}
+
+fn enumerate_trails<'a>(grid: &'a Grid, rules: &'a TrailRules) -> Trails<'a> {
+    Trails {
+        grid,
+        rules,
+        trail: Vec::new(),
+        stack: vec![(positions_at_height(grid, rules.start_height), 0)],
+    }
+}
+
+fn render_heatmap(grid: &Grid, rules: &TrailRules, coloured: bool) -> String {
+    const SHADES: &[u8] = b" .:-=+*#%@";
+    const COLOURS: [u8; 10] = [16, 17, 18, 19, 20, 57, 93, 129, 165, 201];
+
+    let ratings = calculate_ratings(grid, rules);
+    let max_rating = ratings.iter().copied().max().unwrap_or(0).max(1);
+    // Any cell on a trail gets at least the first non-blank shade.
+    let level = |rating: u64| {
+        usize::try_from((rating * 9).div_ceil(max_rating)).expect("Invalid shade level")
+    };
+
+    let mut heatmap = String::new();
+    for (index, (&height, &rating)) in grid.heights.iter().zip(&ratings).enumerate() {
+        match (height, coloured) {
+            (None, false) => heatmap.push('X'),
+            (None, true) => heatmap.push('.'),
+            (Some(_), false) => heatmap.push(char::from(SHADES[level(rating)])),
+            (Some(height), true) => {
+                write!(
+                    heatmap,
+                    "\x1b[48;5;{}m{height}\x1b[0m",
+                    COLOURS[level(rating)]
+                )
+                .unwrap();
+            }
+        }
+        if (index + 1) % grid.cols == 0 {
+            heatmap.push('\n');
+        }
+    }
+    writeln!(heatmap, "Max rating: {max_rating}").unwrap();
+    heatmap
+}
+
+fn format_position(position: Position) -> String {
+    format!("({},{})", position.get_row(), position.get_col())
+}
+
 fn main() {
     let sample_text = helpers::sample_file!();
     let sample_input = parse_input(&sample_text);
@@ -144,8 +325,81 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 81);
 
+    let rules = TrailRules::default();
+    let sample_summits = trailhead_summits(&sample_input, &rules);
+    assert_eq!(sample_summits.len(), 9);
+    assert_eq!(sample_summits[0].0, Position::new(0, 2));
+    assert_eq!(sample_summits[0].1.len(), 5);
+    assert_eq!(enumerate_trails(&sample_input, &rules).count(), 81);
+    let first_trail = enumerate_trails(&sample_input, &rules).next().unwrap();
+    assert_eq!(first_trail.len(), 10);
+    assert_eq!(first_trail[0], Position::new(0, 2));
+    assert!(first_trail
+        .windows(2)
+        .all(|pair| uphill_neighbours(&sample_input, &rules, pair[0]).contains(&pair[1])));
+    let sample_heatmap = render_heatmap(&sample_input, &rules, false);
+    assert!(sample_heatmap.starts_with("..%=@:::\n"));
+    assert!(sample_heatmap.ends_with("Max rating: 24\n"));
+
+    let impassable = parse_input("...0...\n...1...\n...2...\n6543456\n7.....7\n8.....8\n9.....9\n");
+    assert_eq!(part1(&impassable), 2);
+    let impassable = parse_input(".....0.\n..4321.\n..5..2.\n..6543.\n..7..4.\n..8765.\n..9....\n");
+    assert_eq!(part2(&impassable), 3);
+    assert!(render_heatmap(&impassable, &rules, false).starts_with("XXXXX@X\nXX---@X\n"));
+
+    let steep = parse_input("0235\n1111\n");
+    let at_most = |step| TrailRules {
+        start_height: 0,
+        end_height: 3,
+        step: StepRule::AtMost(step),
+    };
+    assert_eq!(total_rating(&steep, &at_most(1)), 0);
+    assert_eq!(total_rating(&steep, &at_most(2)), 1);
+    assert_eq!(total_score(&steep, &at_most(2)), 1);
+    assert_eq!(
+        enumerate_trails(&steep, &at_most(2)).collect::<Vec<_>>(),
+        vec![vec![
+            Position::new(0, 0),
+            Position::new(0, 1),
+            Position::new(0, 2)
+        ]]
+    );
+    let ridge = TrailRules {
+        start_height: 2,
+        end_height: 5,
+        step: StepRule::AtMost(2),
+    };
+    assert_eq!(total_rating(&steep, &ridge), 1);
+
     let text = helpers::input_file!();
     let input = parse_input(&text);
+
+    match std::env::args().nth(1).as_deref() {
+        Some("summits") => {
+            for (start, summits) in trailhead_summits(&input, &rules) {
+                let summits: Vec<String> = summits.into_iter().map(format_position).collect();
+                println!("{}: {}", format_position(start), summits.join(" "));
+            }
+            return;
+        }
+        Some("trails") => {
+            for trail in enumerate_trails(&input, &rules) {
+                let trail: Vec<String> = trail.into_iter().map(format_position).collect();
+                println!("{}", trail.join(" -> "));
+            }
+            return;
+        }
+        Some("heatmap") => {
+            print!("{}", render_heatmap(&input, &rules, false));
+            return;
+        }
+        Some("heatmap-ansi") => {
+            print!("{}", render_heatmap(&input, &rules, true));
+            return;
+        }
+        _ => {}
+    }
+
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
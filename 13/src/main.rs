@@ -1,88 +1,603 @@
-use regex::Regex;
-
-type Case = (i64, i64, i64, i64, i64, i64);
-type Input = Vec<Case>;
-
-fn extract_values(line: &str, regex: &Regex) -> (i64, i64) {
-    regex
-        .captures(line)
-        .map(|captures| {
-            (
-                captures[1].parse::<i64>().unwrap(),
-                captures[2].parse::<i64>().unwrap(),
-            )
-        })
-        .unwrap()
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+struct Vec2 {
+    x: i128,
+    y: i128,
 }
 
-fn parse_input(text: String) -> Input {
-    let a_regex = Regex::new(r"^Button A: X\+(\d+), Y\+(\d+)$").unwrap();
-    let b_regex = Regex::new(r"^Button B: X\+(\d+), Y\+(\d+)$").unwrap();
-    let prize_regex = Regex::new(r"^Prize: X=(\d+), Y=(\d+)$").unwrap();
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Button {
+    label: String,
+    offset: Vec2,
+    tokens: i128,
+}
 
-    text.split("\n\n")
-        .map(|section| {
-            let lines: Vec<&str> = section.lines().collect();
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ClawMachine {
+    buttons: Vec<Button>,
+    prize: Vec2,
+}
 
-            let (a_x, a_y) = extract_values(lines[0], &a_regex);
-            let (b_x, b_y) = extract_values(lines[1], &b_regex);
-            let (prize_x, prize_y) = extract_values(lines[2], &prize_regex);
+type Input = Vec<ClawMachine>;
+
+fn default_tokens(label: &str) -> Option<i128> {
+    match label {
+        "A" => Some(3),
+        "B" => Some(1),
+        _ => None,
+    }
+}
 
-            (a_x, a_y, b_x, b_y, prize_x, prize_y)
+// Parses fields such as `X+94`, `Y = -3` or `Tokens=2`, ignoring any spacing.
+fn parse_fields(text: &str) -> Vec<(String, i128)> {
+    text.split(',')
+        .map(|field| {
+            let field: String = field.chars().filter(|c| !c.is_whitespace()).collect();
+            let split_at = field
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or_else(|| panic!("Missing value in field: {field}"));
+            let (key, value) = field.split_at(split_at);
+            let value = value.strip_prefix('=').unwrap_or(value);
+            let value = value
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid value in field: {field}"));
+            (key.to_uppercase(), value)
         })
         .collect()
 }
 
-fn find_tokens(a_x: i64, a_y: i64, b_x: i64, b_y: i64, prize_x: i64, prize_y: i64) -> i64 {
-    let a_denom = b_y * prize_x - b_x * prize_y;
-    let a_num = a_x * b_y - a_y * b_x;
-    if a_denom % a_num != 0 {
-        return 0;
+fn parse_vec2(fields: &[(String, i128)], line: &str) -> Vec2 {
+    let get = |axis: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == axis)
+            .map(|(_, value)| *value)
+            .unwrap_or_else(|| panic!("Missing {axis} in line: {line}"))
+    };
+    Vec2 {
+        x: get("X"),
+        y: get("Y"),
     }
-    let a_presses = a_denom / a_num;
-    
-    let b_denom = prize_x - a_x * a_presses;
-    if b_denom % b_x != 0 {
-        return 0;
+}
+
+fn parse_machine(section: &[&str]) -> ClawMachine {
+    let mut buttons = Vec::new();
+    let mut prize = None;
+
+    for line in section {
+        let (name, values) = line
+            .split_once(':')
+            .unwrap_or_else(|| panic!("Invalid line: {line}"));
+        let name = name.split_whitespace().collect::<Vec<&str>>();
+        let fields = parse_fields(values);
+
+        match name.as_slice() {
+            [kind, label] if kind.eq_ignore_ascii_case("button") => {
+                let tokens = fields
+                    .iter()
+                    .find(|(key, _)| key == "TOKENS")
+                    .map(|(_, value)| *value)
+                    .or_else(|| default_tokens(label))
+                    .unwrap_or_else(|| panic!("Missing token cost for button {label}"));
+                buttons.push(Button {
+                    label: label.to_string(),
+                    offset: parse_vec2(&fields, line),
+                    tokens,
+                });
+            }
+            [kind] if kind.eq_ignore_ascii_case("prize") => {
+                prize = Some(parse_vec2(&fields, line));
+            }
+            _ => panic!("Invalid line: {line}"),
+        }
+    }
+
+    ClawMachine {
+        buttons,
+        prize: prize.unwrap_or_else(|| panic!("Missing prize in machine: {section:?}")),
     }
-    let b_presses = b_denom / b_x;
-    
-    3 * a_presses + b_presses
 }
 
-fn part1(input: &Input) -> i64 {
-    input
+fn parse_input(text: String) -> Input {
+    let mut machines = Vec::new();
+    let mut section = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !section.is_empty() {
+                machines.push(parse_machine(&section));
+                section.clear();
+            }
+        } else {
+            section.push(line);
+        }
+    }
+    if !section.is_empty() {
+        machines.push(parse_machine(&section));
+    }
+
+    machines
+}
+
+const PRIZE_OFFSET: i128 = 10_000_000_000_000;
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+fn floor_div(num: i128, denom: i128) -> i128 {
+    let quotient = num / denom;
+    if num % denom != 0 && (num < 0) != (denom < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn ceil_div(num: i128, denom: i128) -> i128 {
+    -floor_div(-num, denom)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Unwinnable {
+    NoIntegerSolution,
+    NegativePresses,
+    OverLimit,
+    UnsupportedButtonCount,
+}
+
+impl Unwinnable {
+    fn reason(self) -> &'static str {
+        match self {
+            Unwinnable::NoIntegerSolution => "no integer solution",
+            Unwinnable::NegativePresses => "negative presses",
+            Unwinnable::OverLimit => "over press limit",
+            Unwinnable::UnsupportedButtonCount => "unsupported button count",
+        }
+    }
+}
+
+type Presses = Result<Vec<i128>, Unwinnable>;
+type PressPair = Result<(i128, i128), Unwinnable>;
+type KRange = (Option<i128>, Option<i128>);
+
+fn check_presses(a_presses: i128, b_presses: i128, limit: Option<i128>) -> PressPair {
+    if a_presses < 0 || b_presses < 0 {
+        Err(Unwinnable::NegativePresses)
+    } else if limit.is_some_and(|max| a_presses > max || b_presses > max) {
+        Err(Unwinnable::OverLimit)
+    } else {
+        Ok((a_presses, b_presses))
+    }
+}
+
+// Values of k for which `min <= start + k * step (<= max)`, with `step != 0`.
+fn k_range(start: i128, step: i128, min: i128, max: Option<i128>) -> KRange {
+    if step > 0 {
+        (
+            Some(ceil_div(min - start, step)),
+            max.map(|max| floor_div(max - start, step)),
+        )
+    } else {
+        (
+            max.map(|max| ceil_div(max - start, step)),
+            Some(floor_div(min - start, step)),
+        )
+    }
+}
+
+fn intersect((lo1, hi1): KRange, (lo2, hi2): KRange) -> KRange {
+    let lo = match (lo1, lo2) {
+        (Some(k1), Some(k2)) => Some(k1.max(k2)),
+        _ => lo1.or(lo2),
+    };
+    let hi = match (hi1, hi2) {
+        (Some(k1), Some(k2)) => Some(k1.min(k2)),
+        _ => hi1.or(hi2),
+    };
+    (lo, hi)
+}
+
+fn is_empty((lo, hi): KRange) -> bool {
+    matches!((lo, hi), (Some(lo), Some(hi)) if lo > hi)
+}
+
+// Solves `a * u + b * v = w` for the cheapest non-negative presses within the limit.
+fn solve_collinear(
+    u: i128,
+    v: i128,
+    w: i128,
+    (a_tokens, b_tokens): (i128, i128),
+    limit: Option<i128>,
+) -> PressPair {
+    match (u, v) {
+        (0, 0) if w == 0 => return Ok((0, 0)),
+        (0, 0) => return Err(Unwinnable::NoIntegerSolution),
+        (0, _) if w % v != 0 => return Err(Unwinnable::NoIntegerSolution),
+        (0, _) => return check_presses(0, w / v, limit),
+        (_, 0) if w % u != 0 => return Err(Unwinnable::NoIntegerSolution),
+        (_, 0) => return check_presses(w / u, 0, limit),
+        _ => {}
+    }
+
+    let (g, x, y) = extended_gcd(u, v);
+    if w % g != 0 {
+        return Err(Unwinnable::NoIntegerSolution);
+    }
+
+    // Every solution is (a0 + k * step_a, b0 - k * step_b) for some integer k.
+    let (a0, b0) = (x * (w / g), y * (w / g));
+    let (step_a, step_b) = (v / g, u / g);
+
+    let non_negative = intersect(k_range(a0, step_a, 0, None), k_range(b0, -step_b, 0, None));
+    if is_empty(non_negative) {
+        return Err(Unwinnable::NegativePresses);
+    }
+    let (k_min, k_max) = intersect(
+        non_negative,
+        intersect(
+            k_range(a0, step_a, 0, limit),
+            k_range(b0, -step_b, 0, limit),
+        ),
+    );
+    if is_empty((k_min, k_max)) {
+        return Err(Unwinnable::OverLimit);
+    }
+
+    // The cost is linear in k, so the cheapest solution sits on a bound. The
+    // cost can't decrease forever because presses are non-negative, so the
+    // bound we need always exists.
+    let slope = a_tokens * step_a - b_tokens * step_b;
+    let k = if slope >= 0 {
+        k_min.or(k_max)
+    } else {
+        k_max.or(k_min)
+    }
+    .expect("Unbounded collinear solutions");
+
+    Ok((a0 + k * step_a, b0 - k * step_b))
+}
+
+fn solve_pair(a: &Button, b: &Button, prize: Vec2, limit: Option<i128>) -> PressPair {
+    let (a_x, a_y) = (a.offset.x, a.offset.y);
+    let (b_x, b_y) = (b.offset.x, b.offset.y);
+    let (prize_x, prize_y) = (prize.x, prize.y);
+    let tokens = (a.tokens, b.tokens);
+
+    let det = a_x * b_y - a_y * b_x;
+    if det != 0 {
+        let a_num = b_y * prize_x - b_x * prize_y;
+        let b_num = a_x * prize_y - a_y * prize_x;
+        if a_num % det != 0 || b_num % det != 0 {
+            return Err(Unwinnable::NoIntegerSolution);
+        }
+        return check_presses(a_num / det, b_num / det, limit);
+    }
+
+    // Both buttons move the claw along the same line, so the prize has to lie
+    // on it too. Then one axis is enough to describe every solution.
+    let on_line = |x: i128, y: i128| x * prize_y - y * prize_x == 0;
+    if !on_line(a_x, a_y) || !on_line(b_x, b_y) {
+        return Err(Unwinnable::NoIntegerSolution);
+    }
+    if a_x != 0 || b_x != 0 {
+        let (a_presses, b_presses) = solve_collinear(a_x, b_x, prize_x, tokens, limit)?;
+        if a_presses * a_y + b_presses * b_y != prize_y {
+            return Err(Unwinnable::NoIntegerSolution);
+        }
+        Ok((a_presses, b_presses))
+    } else {
+        solve_collinear(a_y, b_y, prize_y, tokens, limit)
+    }
+}
+
+fn solve(machine: &ClawMachine, prize_offset: i128, limit: Option<i128>) -> Presses {
+    let prize = Vec2 {
+        x: machine.prize.x + prize_offset,
+        y: machine.prize.y + prize_offset,
+    };
+    // The parser takes any number of buttons, but only pairs can be solved:
+    // other machines are reported as unwinnable and left out of the totals.
+    match machine.buttons.as_slice() {
+        [a, b] => {
+            solve_pair(a, b, prize, limit).map(|(a_presses, b_presses)| vec![a_presses, b_presses])
+        }
+        _ => Err(Unwinnable::UnsupportedButtonCount),
+    }
+}
+
+fn tokens(machine: &ClawMachine, presses: &[i128]) -> i128 {
+    machine
+        .buttons
         .iter()
-        .map(|(a_x, a_y, b_x, b_y, prize_x, prize_y)| {
-            find_tokens(*a_x, *a_y, *b_x, *b_y, *prize_x, *prize_y)
-        })
+        .zip(presses)
+        .map(|(button, presses)| button.tokens * presses)
         .sum()
 }
 
-fn part2(input: &Input) -> i64 {
+fn machine_presses(input: &Input, prize_offset: i128, limit: Option<i128>) -> Vec<Presses> {
     input
         .iter()
-        .map(|(a_x, a_y, b_x, b_y, prize_x, prize_y)| {
-            find_tokens(
-                *a_x,
-                *a_y,
-                *b_x,
-                *b_y,
-                *prize_x + 10_000_000_000_000,
-                *prize_y + 10_000_000_000_000,
-            )
+        .map(|machine| solve(machine, prize_offset, limit))
+        .collect()
+}
+
+// Every button label in order of first appearance, so reports get one
+// column per button.
+fn button_labels(input: &Input) -> Vec<&str> {
+    let mut labels: Vec<&str> = Vec::new();
+    for button in input.iter().flat_map(|machine| &machine.buttons) {
+        if !labels.contains(&button.label.as_str()) {
+            labels.push(&button.label);
+        }
+    }
+    labels
+}
+
+fn presses_for(machine: &ClawMachine, presses: &[i128], label: &str) -> Option<i128> {
+    machine
+        .buttons
+        .iter()
+        .position(|button| button.label == label)
+        .map(|i| presses[i])
+}
+
+fn report_table(input: &Input, presses: &[Presses]) -> String {
+    let labels = button_labels(input);
+
+    let mut table = format!("{:>7}", "Machine");
+    for label in &labels {
+        table.push_str(&format!("  {:>15}", format!("{label} presses")));
+    }
+    table.push_str(&format!("  {:>16}  Status\n", "Tokens"));
+
+    for (i, (machine, result)) in input.iter().zip(presses).enumerate() {
+        table.push_str(&format!("{:>7}", i + 1));
+        for label in &labels {
+            let cell = match result {
+                Ok(presses) => presses_for(machine, presses, label)
+                    .map_or(String::from("-"), |count| count.to_string()),
+                Err(_) => String::from("-"),
+            };
+            table.push_str(&format!("  {cell:>15}"));
+        }
+        let row = match result {
+            Ok(presses) => format!("  {:>16}  ok\n", tokens(machine, presses)),
+            Err(unwinnable) => format!("  {:>16}  {}\n", "-", unwinnable.reason()),
+        };
+        table.push_str(&row);
+    }
+    table
+}
+
+// Quotes fields holding separators or quotes, doubling the quotes (RFC 4180).
+fn escape_csv(field: &str) -> String {
+    if field.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn report_csv(input: &Input, presses: &[Presses]) -> String {
+    let labels = button_labels(input);
+
+    let mut csv = String::from("machine");
+    for label in &labels {
+        csv.push_str(&format!(
+            ",{}",
+            escape_csv(&format!("{}_presses", label.to_lowercase()))
+        ));
+    }
+    csv.push_str(",tokens,status\n");
+
+    for (i, (machine, result)) in input.iter().zip(presses).enumerate() {
+        csv.push_str(&(i + 1).to_string());
+        for label in &labels {
+            let cell = match result {
+                Ok(presses) => presses_for(machine, presses, label)
+                    .map_or(String::new(), |count| count.to_string()),
+                Err(_) => String::new(),
+            };
+            csv.push_str(&format!(",{cell}"));
+        }
+        let row = match result {
+            Ok(presses) => format!(",{},ok\n", tokens(machine, presses)),
+            Err(unwinnable) => format!(",,{}\n", unwinnable.reason()),
+        };
+        csv.push_str(&row);
+    }
+    csv
+}
+
+fn escape_json(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn report_json(input: &Input, presses: &[Presses]) -> String {
+    let labels = button_labels(input);
+
+    let rows: Vec<String> = input
+        .iter()
+        .zip(presses)
+        .enumerate()
+        .map(|(i, (machine, result))| {
+            let mut fields = vec![format!("\"machine\": {}", i + 1)];
+            for label in &labels {
+                let count = match result {
+                    Ok(presses) => presses_for(machine, presses, label)
+                        .map_or(String::from("null"), |count| count.to_string()),
+                    Err(_) => String::from("null"),
+                };
+                fields.push(format!(
+                    "\"{}_presses\": {count}",
+                    escape_json(&label.to_lowercase())
+                ));
+            }
+            match result {
+                Ok(presses) => {
+                    fields.push(format!("\"tokens\": {}", tokens(machine, presses)));
+                    fields.push(String::from("\"status\": \"ok\""));
+                }
+                Err(unwinnable) => {
+                    fields.push(String::from("\"tokens\": null"));
+                    fields.push(format!("\"status\": \"{}\"", unwinnable.reason()));
+                }
+            }
+            format!("  {{{}}}", fields.join(", "))
         })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+fn total_tokens(input: &Input, prize_offset: i128, limit: Option<i128>) -> i128 {
+    input
+        .iter()
+        .zip(machine_presses(input, prize_offset, limit))
+        .filter_map(|(machine, result)| result.ok().map(|presses| tokens(machine, &presses)))
         .sum()
 }
 
+fn part1(input: &Input) -> i128 {
+    total_tokens(input, 0, Some(100))
+}
+
+fn part2(input: &Input) -> i128 {
+    total_tokens(input, PRIZE_OFFSET, None)
+}
+
 fn main() {
     let sample_text = helpers::sample_file!();
     let sample_input = parse_input(sample_text);
     let sample_answer1 = part1(&sample_input);
     assert_eq!(sample_answer1, 480);
+    let sample_answer2 = part2(&sample_input);
+    assert_eq!(sample_answer2, 875_318_608_908);
+
+    let machine = |a: (i128, i128), b: (i128, i128), prize: (i128, i128)| {
+        parse_input(format!(
+            "Button A: X{:+}, Y{:+}\nButton B: X{:+}, Y{:+}\nPrize: X={}, Y={}\n",
+            a.0, a.1, b.0, b.1, prize.0, prize.1
+        ))
+        .remove(0)
+    };
+    let solve_for = |a, b, prize, limit| solve(&machine(a, b, prize), 0, limit);
+    assert_eq!(solve_for((1, 1), (2, 2), (10, 10), None), Ok(vec![0, 5]));
+    assert_eq!(solve_for((1, 1), (2, 2), (10, 10), Some(4)), Ok(vec![2, 4]));
+    assert_eq!(
+        solve_for((1, 1), (2, 2), (10, 10), Some(2)),
+        Err(Unwinnable::OverLimit)
+    );
+    assert_eq!(solve_for((4, 4), (1, 1), (8, 8), None), Ok(vec![2, 0]));
+    assert_eq!(
+        solve_for((1, 1), (2, 2), (10, 11), None),
+        Err(Unwinnable::NoIntegerSolution)
+    );
+    assert_eq!(
+        solve_for((2, 4), (3, 6), (1, 2), None),
+        Err(Unwinnable::NegativePresses)
+    );
+    assert_eq!(solve_for((0, 0), (0, 0), (0, 0), None), Ok(vec![0, 0]));
+    assert_eq!(
+        solve_for((2, 1), (1, 2), (1, -1), None),
+        Err(Unwinnable::NegativePresses)
+    );
+    assert_eq!(
+        solve_for((94, 34), (22, 67), (8400, 5400), Some(50)),
+        Err(Unwinnable::OverLimit)
+    );
+
+    let sample_presses = machine_presses(&sample_input, 0, Some(100));
+    assert_eq!(
+        report_csv(&sample_input, &sample_presses),
+        "machine,a_presses,b_presses,tokens,status\n\
+         1,80,40,280,ok\n\
+         2,,,,no integer solution\n\
+         3,38,86,200,ok\n\
+         4,,,,no integer solution\n"
+    );
+    assert!(report_table(&sample_input, &sample_presses)
+        .contains("      1               80               40               280  ok\n"));
+    assert!(
+        report_json(&sample_input, &sample_presses).contains("\"machine\": 2, \"a_presses\": null")
+    );
+
+    let messy_input = parse_input(String::from(
+        "Prize: X = 10, Y=+4\r\n  Button B:X-1 ,Y+ 2 , Tokens=5\r\nButton A: X+3, Y+0\r\n\r\n \r\n\
+         Button C: X=2, Y=2, Tokens=4\r\nButton D: X=1, Y=0, Tokens=1\r\n\
+         Button E: X=0, Y=1, Tokens=2\r\nPrize: X=6, Y=6\r\n\r\n\
+         Button C: X=2, Y=2, Tokens=4\r\nPrize: X=6, Y=6\r\n",
+    ));
+    assert_eq!(
+        messy_input[0],
+        ClawMachine {
+            buttons: vec![
+                Button {
+                    label: String::from("B"),
+                    offset: Vec2 { x: -1, y: 2 },
+                    tokens: 5,
+                },
+                Button {
+                    label: String::from("A"),
+                    offset: Vec2 { x: 3, y: 0 },
+                    tokens: 3,
+                },
+            ],
+            prize: Vec2 { x: 10, y: 4 },
+        }
+    );
+    assert_eq!(messy_input[1].buttons.len(), 3);
+    let messy_presses = machine_presses(&messy_input, 0, None);
+    assert_eq!(
+        messy_presses,
+        vec![
+            Ok(vec![2, 4]),
+            Err(Unwinnable::UnsupportedButtonCount),
+            Err(Unwinnable::UnsupportedButtonCount)
+        ]
+    );
+    assert_eq!(total_tokens(&messy_input, 0, None), 22);
+    assert_eq!(
+        report_csv(&messy_input, &messy_presses),
+        "machine,b_presses,a_presses,c_presses,d_presses,e_presses,tokens,status\n\
+         1,2,4,,,,22,ok\n\
+         2,,,,,,,unsupported button count\n\
+         3,,,,,,,unsupported button count\n"
+    );
+    assert!(report_json(&messy_input, &messy_presses)
+        .contains("\"machine\": 3, \"b_presses\": null, \"a_presses\": null, \"c_presses\": null"));
+
+    let quoted_input = parse_input(String::from(
+        "Button \"A\": X+1, Y+0, Tokens=1\nButton B,C: X+0, Y+1, Tokens=1\nPrize: X=2, Y=3\n",
+    ));
+    let quoted_presses = machine_presses(&quoted_input, 0, None);
+    assert!(report_json(&quoted_input, &quoted_presses)
+        .contains("{\"machine\": 1, \"\\\"a\\\"_presses\": 2, \"b,c_presses\": 3"));
+    assert_eq!(
+        report_csv(&quoted_input, &quoted_presses),
+        "machine,\"\"\"a\"\"_presses\",\"b,c_presses\",tokens,status\n1,2,3,5,ok\n"
+    );
 
     let text = helpers::input_file!();
     let input = parse_input(text);
+
+    if let Some(format) = std::env::args().nth(1) {
+        let presses = match std::env::args().nth(2).as_deref() {
+            Some("2") => machine_presses(&input, PRIZE_OFFSET, None),
+            _ => machine_presses(&input, 0, Some(100)),
+        };
+        match format.as_str() {
+            "table" => print!("{}", report_table(&input, &presses)),
+            "csv" => print!("{}", report_csv(&input, &presses)),
+            "json" => print!("{}", report_json(&input, &presses)),
+            _ => panic!("Unknown report format: {format}"),
+        }
+        return;
+    }
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
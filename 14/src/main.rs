@@ -1,108 +1,466 @@
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 type Position = (i32, i32);
 type Velocity = (i32, i32);
 
-fn parse_input(text: String) -> Vec<(Position, Velocity)> {
-    let regex = Regex::new(r"p=(\d+),(\d+) v=(-?\d+),(-?\d+)").unwrap();
+struct Room {
+    width: i32,
+    height: i32,
+}
+
+impl Room {
+    fn quadrant(&self, (x, y): Position) -> Option<usize> {
+        let mid_x = self.width / 2;
+        let mid_y = self.height / 2;
+
+        match (x < mid_x, x > mid_x, y < mid_y, y > mid_y) {
+            (true, false, true, false) => Some(0),
+            (false, true, true, false) => Some(1),
+            (true, false, false, true) => Some(2),
+            (false, true, false, true) => Some(3),
+            _ => None,
+        }
+    }
+}
+
+struct Robot {
+    pos: Position,
+    vel: Velocity,
+}
+
+impl Robot {
+    fn position_at(&self, seconds: i64, room: &Room) -> Position {
+        let axis = |pos: i32, vel: i32, size: i32| {
+            let size = i64::from(size);
+            // Reducing the time first keeps the product small for any t.
+            let moved = i64::from(pos) + seconds.rem_euclid(size) * i64::from(vel);
+            i32::try_from(moved.rem_euclid(size)).expect("Invalid position")
+        };
+        (
+            axis(self.pos.0, self.vel.0, room.width),
+            axis(self.pos.1, self.vel.1, room.height),
+        )
+    }
+}
+
+fn parse_input(text: String) -> Vec<Robot> {
+    let regex = Regex::new(r"p=(-?\d+),(-?\d+) v=(-?\d+),(-?\d+)").unwrap();
     text.lines()
         .map(|line| {
             regex
                 .captures(line)
-                .map(|captures| {
-                    (
-                        (
-                            captures.get(1).unwrap().as_str().parse::<i32>().unwrap(),
-                            captures.get(2).unwrap().as_str().parse::<i32>().unwrap(),
-                        ),
-                        (
-                            captures.get(3).unwrap().as_str().parse::<i32>().unwrap(),
-                            captures.get(4).unwrap().as_str().parse::<i32>().unwrap(),
-                        ),
-                    )
+                .map(|captures| Robot {
+                    pos: (
+                        captures.get(1).unwrap().as_str().parse::<i32>().unwrap(),
+                        captures.get(2).unwrap().as_str().parse::<i32>().unwrap(),
+                    ),
+                    vel: (
+                        captures.get(3).unwrap().as_str().parse::<i32>().unwrap(),
+                        captures.get(4).unwrap().as_str().parse::<i32>().unwrap(),
+                    ),
                 })
                 .unwrap()
         })
         .collect()
 }
 
-fn part1(input: &[(Position, Velocity)], max_x: i32, max_y: i32) -> i32 {
-    let mut top_left = 0;
-    let mut top_right = 0;
-    let mut bottom_left = 0;
-    let mut bottom_right = 0;
+fn positions_at(robots: &[Robot], seconds: i64, room: &Room) -> Vec<Position> {
+    robots
+        .iter()
+        .map(|robot| robot.position_at(seconds, room))
+        .collect()
+}
 
-    let mid_x = max_x / 2;
-    let mid_y = max_y / 2;
+fn safety_factor(robots: &[Robot], seconds: i64, room: &Room) -> i32 {
+    let mut quadrants = [0; 4];
+    for position in positions_at(robots, seconds, room) {
+        if let Some(quadrant) = room.quadrant(position) {
+            quadrants[quadrant] += 1;
+        }
+    }
+    quadrants.iter().product()
+}
 
-    for robot in input {
-        let ((pos_x, pos_y), (vel_x, vel_y)) = robot;
-        let x = (pos_x + 100 * vel_x).rem_euclid(max_x);
-        let y = (pos_y + 100 * vel_y).rem_euclid(max_y);
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
 
-        match (x < mid_x, x > mid_x, y < mid_y, y > mid_y) {
-            (true, false, true, false) => top_left += 1,
-            (true, false, false, true) => bottom_left += 1,
-            (false, true, true, false) => top_right += 1,
-            (false, true, false, true) => bottom_right += 1,
-            _ => (),
+fn lcm(a: i64, b: i64) -> i64 {
+    a / gcd(a, b) * b
+}
+
+// Each robot comes back to the same column every width / gcd(vel_x, width)
+// seconds (and likewise for rows), so the whole room repeats after the lcm
+// of all those periods.
+fn period(robots: &[Robot], room: &Room) -> i64 {
+    let axis_period = |size: i32, velocity: fn(&Robot) -> i32| {
+        let size = i64::from(size);
+        robots
+            .iter()
+            .map(|robot| size / gcd(i64::from(velocity(robot)), size))
+            .fold(1, lcm)
+    };
+    lcm(
+        axis_period(room.width, |robot| robot.vel.0),
+        axis_period(room.height, |robot| robot.vel.1),
+    )
+}
+
+fn part1(robots: &[Robot], room: &Room) -> i32 {
+    safety_factor(robots, 100, room)
+}
+
+fn variance(values: impl Iterator<Item = i32>) -> f64 {
+    let values: Vec<f64> = values.map(f64::from).collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64
+}
+
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// Smallest non-negative t with t = a (mod m) and t = b (mod n), if any.
+fn chinese_remainder(a: i64, m: i64, b: i64, n: i64) -> Option<i64> {
+    let (g, p, _) = extended_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+
+    let lcm = m / g * n;
+    let k = ((b - a) / g * p).rem_euclid(n / g);
+    Some((a + m * k).rem_euclid(lcm))
+}
+
+// The x coordinates repeat every `width` seconds and the y coordinates every
+// `height`, so the most tightly packed second on each axis can be found
+// separately and then combined.
+fn variance_candidate(robots: &[Robot], room: &Room) -> Option<i64> {
+    let tightest = |period: i32, axis: fn(&Position) -> i32| {
+        (0..i64::from(period))
+            .map(|seconds| {
+                let positions = positions_at(robots, seconds, room);
+                (seconds, variance(positions.iter().map(axis)))
+            })
+            .min_by(|(_, variance1), (_, variance2)| variance1.total_cmp(variance2))
+            .map(|(seconds, _)| seconds)
+            .unwrap()
+    };
+
+    let best_x = tightest(room.width, |position| position.0);
+    let best_y = tightest(room.height, |position| position.1);
+    chinese_remainder(
+        best_x,
+        i64::from(room.width),
+        best_y,
+        i64::from(room.height),
+    )
+}
+
+// Number of robots standing right next to another robot.
+fn cluster_score(robots: &[Position]) -> usize {
+    let robots_set = robots.iter().copied().collect::<HashSet<Position>>();
+    robots_set
+        .iter()
+        .filter(|(x, y)| {
+            [(x - 1, *y), (x + 1, *y), (*x, y - 1), (*x, y + 1)]
+                .iter()
+                .any(|neighbour| robots_set.contains(neighbour))
+        })
+        .count()
+}
+
+// Shannon entropy of the robot counts over square blocks of the room: the
+// more ordered the picture, the lower the entropy.
+fn entropy(robots: &[Position], block_size: i32) -> f64 {
+    let mut counts = HashMap::new();
+    for (x, y) in robots {
+        *counts.entry((x / block_size, y / block_size)).or_insert(0) += 1;
+    }
+
+    let total = robots.len() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = f64::from(count) / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn rank_candidates(robots: &[Robot], room: &Room, count: usize) -> Vec<i64> {
+    // Nothing new can show up once the whole room repeats, which happens
+    // after width * height seconds at the latest.
+    let mut scored: Vec<(i64, usize, f64)> = (0..period(robots, room))
+        .map(|seconds| {
+            let positions = positions_at(robots, seconds, room);
+            (seconds, cluster_score(&positions), entropy(&positions, 4))
+        })
+        .collect();
+    scored.sort_by(
+        |(seconds1, cluster1, entropy1), (seconds2, cluster2, entropy2)| {
+            cluster2
+                .cmp(cluster1)
+                .then(entropy1.total_cmp(entropy2))
+                .then(seconds1.cmp(seconds2))
+        },
+    );
+
+    let mut candidates: Vec<i64> = variance_candidate(robots, room).into_iter().collect();
+    for (seconds, _, _) in scored {
+        if candidates.len() >= count {
+            break;
+        }
+        if !candidates.contains(&seconds) {
+            candidates.push(seconds);
         }
     }
+    candidates
+}
 
-    top_left * top_right * bottom_left * bottom_right
+fn part2(robots: &[Robot], room: &Room) -> i64 {
+    let candidates = rank_candidates(robots, room, 5);
+    candidates[0]
 }
 
-#[allow(dead_code)]
-fn print_map(robots: &[Position], max_x: i32, max_y: i32) {
+fn robot_counts(robots: &[Position]) -> HashMap<Position, u32> {
+    let mut counts = HashMap::new();
+    for robot in robots {
+        *counts.entry(*robot).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Plain (ASCII) PBM: 1 is a black pixel, i.e. at least one robot.
+fn render_pbm(robots: &[Position], room: &Room) -> String {
     let robots_set = robots.iter().copied().collect::<HashSet<Position>>();
-    for y in 0..max_y {
-        for x in 0..max_x {
-            print!(
-                "{}",
+    let mut image = format!("P1\n{} {}\n", room.width, room.height);
+    for y in 0..room.height {
+        let row: Vec<&str> = (0..room.width)
+            .map(|x| {
                 if robots_set.contains(&(x, y)) {
-                    '*'
+                    "1"
                 } else {
-                    '.'
+                    "0"
                 }
-            );
-        }
-        println!();
+            })
+            .collect();
+        image.push_str(&row.join(" "));
+        image.push('\n');
     }
+    image
 }
 
-fn part2(input: &[(Position, Velocity)], max_x: i32, max_y: i32) -> i32 {
-    let mut seconds = 0;
-    let mut robots = input.iter().map(|robot| robot.0).collect::<Vec<_>>();
+// Plain (ASCII) PGM: the more robots share a tile, the darker it gets.
+fn render_pgm(robots: &[Position], room: &Room) -> String {
+    let counts = robot_counts(robots);
+    let max_count = counts.values().copied().max().unwrap_or(0).max(1);
+    let mut image = format!("P2\n{} {}\n{max_count}\n", room.width, room.height);
+    for y in 0..room.height {
+        let row: Vec<String> = (0..room.width)
+            .map(|x| (max_count - counts.get(&(x, y)).copied().unwrap_or(0)).to_string())
+            .collect();
+        image.push_str(&row.join(" "));
+        image.push('\n');
+    }
+    image
+}
 
-    loop {
-        seconds += 1;
+fn write_frames(
+    robots: &[Robot],
+    seconds: std::ops::RangeInclusive<i64>,
+    room: &Room,
+    directory: &std::path::Path,
+    greyscale: bool,
+) {
+    std::fs::create_dir_all(directory).expect("Unable to create the frames directory");
+    for second in seconds {
+        let positions = positions_at(robots, second, room);
+        let (image, extension) = if greyscale {
+            (render_pgm(&positions, room), "pgm")
+        } else {
+            (render_pbm(&positions, room), "pbm")
+        };
+        std::fs::write(
+            directory.join(format!("frame_{second:05}.{extension}")),
+            image,
+        )
+        .expect("Unable to write frame");
+    }
+}
 
-        let mut robots_set = HashSet::new();
-        for (i, (_, (vel_x, vel_y))) in input.iter().enumerate() {
-            let (curr_x, curr_y) = robots[i];
-            let new_position = ((curr_x + vel_x).rem_euclid(max_x), (curr_y + vel_y).rem_euclid(max_y));
-            robots[i] = new_position;
-            robots_set.insert(new_position);
-        }
+// Tiles one frame per second into a single PGM, separated by grey lines.
+fn contact_sheet(
+    robots: &[Robot],
+    seconds: std::ops::RangeInclusive<i64>,
+    columns: i32,
+    room: &Room,
+) -> String {
+    assert!(!seconds.is_empty(), "No frames in {seconds:?}");
+    let (max_x, max_y) = (room.width, room.height);
+    let frames: Vec<HashSet<Position>> = seconds
+        .map(|second| positions_at(robots, second, room).into_iter().collect())
+        .collect();
+    let num_frames = i32::try_from(frames.len()).expect("Too many frames");
+    let columns = columns.min(num_frames).max(1);
+    let rows = (num_frames + columns - 1) / columns;
+    let width = columns * (max_x + 1) - 1;
+    let height = rows * (max_y + 1) - 1;
 
-        if robots_set.len() == robots.len() {
-            // print_map(&robots, max_x, max_y);
-            return seconds;
-        }
+    let mut image = format!("P2\n{width} {height}\n255\n");
+    for y in 0..height {
+        let row: Vec<&str> = (0..width)
+            .map(|x| {
+                if x % (max_x + 1) == max_x || y % (max_y + 1) == max_y {
+                    return "128";
+                }
+                let frame = (y / (max_y + 1)) * columns + x / (max_x + 1);
+                let position = (x % (max_x + 1), y % (max_y + 1));
+                match frames.get(usize::try_from(frame).unwrap()) {
+                    Some(robots) if robots.contains(&position) => "0",
+                    _ => "255",
+                }
+            })
+            .collect();
+        image.push_str(&row.join(" "));
+        image.push('\n');
     }
+    image
 }
 
 fn main() {
     let sample_text = helpers::sample_file!();
     let sample_input = parse_input(sample_text);
-    let sample_answer1 = part1(&sample_input, 11, 7);
+    let sample_room = Room {
+        width: 11,
+        height: 7,
+    };
+    let sample_answer1 = part1(&sample_input, &sample_room);
     assert_eq!(sample_answer1, 12);
+    assert_eq!(chinese_remainder(2, 3, 3, 5), Some(8));
+    assert_eq!(chinese_remainder(1, 4, 2, 6), None);
+    assert_eq!(rank_candidates(&sample_input, &sample_room, 3).len(), 3);
+
+    let sample_period = period(&sample_input, &sample_room);
+    assert_eq!(sample_period, 77);
+    assert_eq!(
+        safety_factor(&sample_input, 100 + 1_000_000 * sample_period, &sample_room),
+        12
+    );
+    let robot = Robot {
+        pos: (-1, 9),
+        vel: (2, -3),
+    };
+    assert_eq!(robot.position_at(0, &sample_room), (10, 2));
+    assert_eq!(robot.position_at(5, &sample_room), (9, 1));
+    assert_eq!(
+        robot.position_at(5 + 77 * 1_000_000_000_000, &sample_room),
+        (9, 1)
+    );
+    let robots = parse_input(String::from("p=-1,9 v=2,-3\n"));
+    assert_eq!(robots[0].position_at(5, &sample_room), (9, 1));
+
+    let tiny_room = Room {
+        width: 3,
+        height: 2,
+    };
+    let tiny_input = vec![
+        Robot {
+            pos: (0, 0),
+            vel: (1, 0),
+        },
+        Robot {
+            pos: (2, 1),
+            vel: (0, 0),
+        },
+    ];
+    assert_eq!(
+        render_pbm(&positions_at(&tiny_input, 0, &tiny_room), &tiny_room),
+        "P1\n3 2\n1 0 0\n0 0 1\n"
+    );
+    assert_eq!(
+        render_pgm(
+            &[(0, 0), (0, 0), (1, 0)],
+            &Room {
+                width: 3,
+                height: 1
+            }
+        ),
+        "P2\n3 1\n2\n0 1 2\n"
+    );
+    assert_eq!(
+        contact_sheet(&tiny_input, 0..=1, 2, &tiny_room),
+        "P2\n7 2\n255\n0 255 255 128 255 0 255\n255 255 0 128 255 255 0\n"
+    );
 
     let text = helpers::input_file!();
     let input = parse_input(text);
-    let answer1 = part1(&input, 101, 103);
+    let room = Room {
+        width: 101,
+        height: 103,
+    };
+
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("period") => {
+            println!("{}", period(&input, &room));
+            return;
+        }
+        Some("safety") => {
+            let seconds = args.get(2).map_or(100, |seconds| {
+                seconds.parse().expect("Invalid number of seconds")
+            });
+            println!("{}", safety_factor(&input, seconds, &room));
+            return;
+        }
+        Some(mode @ ("frames" | "sheet")) => {
+            assert!(
+                args.len() >= 5,
+                "Usage: {mode} FIRST LAST PATH [{}]",
+                if mode == "frames" { "pgm" } else { "COLUMNS" }
+            );
+            let from: i64 = args[2].parse().expect("Invalid first second");
+            let to: i64 = args[3].parse().expect("Invalid last second");
+            assert!(
+                from <= to,
+                "The first second {from} comes after the last one {to}"
+            );
+            if mode == "frames" {
+                write_frames(
+                    &input,
+                    from..=to,
+                    &room,
+                    std::path::Path::new(&args[4]),
+                    args.get(5).is_some_and(|format| format == "pgm"),
+                );
+            } else {
+                let columns = args.get(5).map_or(10, |columns| {
+                    columns.parse().expect("Invalid number of columns")
+                });
+                std::fs::write(&args[4], contact_sheet(&input, from..=to, columns, &room))
+                    .expect("Unable to write contact sheet");
+            }
+            return;
+        }
+        Some(mode) => panic!("Unknown mode: {mode}"),
+        None => {}
+    }
+    let answer1 = part1(&input, &room);
     println!("{answer1}");
-    let answer2 = part2(&input, 101, 103);
+    let answer2 = part2(&input, &room);
     println!("{answer2}");
 }
@@ -4,12 +4,16 @@ use helpers::position_with_direction::PositionWithDirection;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
-type Input = (Position, Position, HashSet<Position>);
+type TileCosts = HashMap<Position, u32>;
+type Input = (Position, Position, HashSet<Position>, TileCosts);
+type Distances = HashMap<PositionWithDirection, u32>;
+type BestPaths = (Distances, Distances, u32);
 
 fn parse_input(text: String) -> Input {
     let mut start = Position::new(-1, -1);
     let mut end = Position::new(-1, -1);
     let mut walls = HashSet::new();
+    let mut tile_costs = HashMap::new();
 
     for (r, row) in text.lines().enumerate() {
         for (c, cell) in row.chars().enumerate() {
@@ -19,12 +23,48 @@ fn parse_input(text: String) -> Input {
                 '#' => {
                     walls.insert(Position::from_usize(r, c));
                 }
+                '0'..='9' => {
+                    tile_costs.insert(Position::from_usize(r, c), cell.to_digit(10).unwrap());
+                }
                 _ => {}
             }
         }
     }
 
-    (start, end, walls)
+    (start, end, walls, tile_costs)
+}
+
+struct CostModel {
+    forward_cost: u32,
+    turn_cost: u32,
+    u_turn_cost: Option<u32>,
+    start_direction: Direction,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            forward_cost: 1,
+            turn_cost: 1000,
+            u_turn_cost: None,
+            start_direction: Direction::EAST,
+        }
+    }
+}
+
+impl CostModel {
+    fn step_cost(&self, tile: Position, tile_costs: &TileCosts) -> u32 {
+        tile_costs.get(&tile).copied().unwrap_or(self.forward_cost)
+    }
+
+    // Computed once per search: scaling the estimate by the cheapest possible
+    // step keeps it from ever overshooting.
+    fn cheapest_step(&self, tile_costs: &TileCosts) -> u32 {
+        tile_costs
+            .values()
+            .copied()
+            .fold(self.forward_cost, u32::min)
+    }
 }
 
 #[derive(Eq, PartialEq)]
@@ -32,7 +72,6 @@ struct State {
     position_with_direction: PositionWithDirection,
     score: u32,
     estimated_distance: u32,
-    path: HashSet<Position>,
 }
 
 impl PartialOrd<Self> for State {
@@ -51,83 +90,98 @@ impl Ord for State {
 }
 
 fn manhattan(curr: Position, end: Position) -> u32 {
-    u32::try_from(curr.get_row() - end.get_row().abs() + (curr.get_col() - end.get_col()).abs())
+    u32::try_from((curr.get_row() - end.get_row()).abs() + (curr.get_col() - end.get_col()).abs())
         .expect("Invalid manhattan distance")
 }
 
+fn next_position(position: Position, direction: Direction) -> Position {
+    match direction {
+        Direction::NORTH => position.up(),
+        Direction::EAST => position.right(),
+        Direction::SOUTH => position.down(),
+        Direction::WEST => position.left(),
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::NORTH => Direction::SOUTH,
+        Direction::EAST => Direction::WEST,
+        Direction::SOUTH => Direction::NORTH,
+        Direction::WEST => Direction::EAST,
+    }
+}
+
+fn rotations(direction: Direction, costs: &CostModel) -> Vec<(Direction, u32)> {
+    let (next_direction_clockwise, next_direction_counterclockwise) = match direction {
+        Direction::NORTH => (Direction::EAST, Direction::WEST),
+        Direction::EAST => (Direction::SOUTH, Direction::NORTH),
+        Direction::SOUTH => (Direction::WEST, Direction::EAST),
+        Direction::WEST => (Direction::NORTH, Direction::SOUTH),
+    };
+
+    let mut rotations = vec![
+        (next_direction_clockwise, costs.turn_cost),
+        (next_direction_counterclockwise, costs.turn_cost),
+    ];
+    if let Some(u_turn_cost) = costs.u_turn_cost {
+        rotations.push((opposite(direction), u_turn_cost));
+    }
+    rotations
+}
+
 fn handle_move_forwards(
     priority_queue: &mut BinaryHeap<State>,
     curr_state: &State,
-    end: Position,
-    walls: &HashSet<Position>,
+    (_, end, walls, tile_costs): &Input,
+    costs: &CostModel,
+    cheapest_step: u32,
 ) {
     let curr_position = curr_state.position_with_direction.get_position();
     let curr_direction = curr_state.position_with_direction.get_direction();
 
-    let next_step = match curr_direction {
-        Direction::NORTH => curr_position.up(),
-        Direction::EAST => curr_position.right(),
-        Direction::SOUTH => curr_position.down(),
-        Direction::WEST => curr_position.left(),
-    };
+    let next_step = next_position(curr_position, curr_direction);
     if !walls.contains(&next_step) {
-        let mut new_path = curr_state.path.clone();
-        new_path.insert(curr_position);
-        
         priority_queue.push(State {
             position_with_direction: PositionWithDirection::from_position_and_direction(
                 next_step,
                 curr_direction,
             ),
-            score: curr_state.score + 1,
-            estimated_distance: manhattan(next_step, end),
-            path: new_path,
+            score: curr_state.score + costs.step_cost(next_step, tile_costs),
+            estimated_distance: manhattan(next_step, *end) * cheapest_step,
         });
     }
 }
 
-fn handle_rotate(priority_queue: &mut BinaryHeap<State>, curr_state: &State) {
+fn handle_rotate(priority_queue: &mut BinaryHeap<State>, curr_state: &State, costs: &CostModel) {
     let curr_position = curr_state.position_with_direction.get_position();
     let curr_direction = curr_state.position_with_direction.get_direction();
 
-    let (next_direction_clockwise, next_direction_counterclockwise) = match curr_direction {
-        Direction::NORTH => (Direction::EAST, Direction::WEST),
-        Direction::EAST => (Direction::SOUTH, Direction::NORTH),
-        Direction::SOUTH => (Direction::WEST, Direction::EAST),
-        Direction::WEST => (Direction::NORTH, Direction::SOUTH),
-    };
-    priority_queue.push(State {
-        position_with_direction: PositionWithDirection::from_position_and_direction(
-            curr_position,
-            next_direction_clockwise,
-        ),
-        score: curr_state.score + 1000,
-        estimated_distance: curr_state.estimated_distance,
-        path: curr_state.path.clone(),
-    });
-    priority_queue.push(State {
-        position_with_direction: PositionWithDirection::from_position_and_direction(
-            curr_position,
-            next_direction_counterclockwise,
-        ),
-        score: curr_state.score + 1000,
-        estimated_distance: curr_state.estimated_distance,
-        path: curr_state.path.clone(),
-    });
+    for (next_direction, cost) in rotations(curr_direction, costs) {
+        priority_queue.push(State {
+            position_with_direction: PositionWithDirection::from_position_and_direction(
+                curr_position,
+                next_direction,
+            ),
+            score: curr_state.score + cost,
+            estimated_distance: curr_state.estimated_distance,
+        });
+    }
 }
 
-fn part1((start, end, walls): &Input) -> u32 {
+fn lowest_score(input: &Input, costs: &CostModel) -> u32 {
+    let (start, end, _, tile_costs) = input;
     let mut priority_queue = BinaryHeap::new();
     let mut visited = HashSet::<PositionWithDirection>::new();
+    let cheapest_step = costs.cheapest_step(tile_costs);
 
     let start_state = State {
         position_with_direction: PositionWithDirection::from_position_and_direction(
             *start,
-            Direction::EAST,
+            costs.start_direction,
         ),
         score: 0,
-        estimated_distance: manhattan(*start, *end),
-        path: HashSet::new(),
+        estimated_distance: manhattan(*start, *end) * cheapest_step,
     };
     priority_queue.push(start_state);
     while !priority_queue.is_empty() {
@@ -145,62 +199,270 @@ fn part1((start, end, walls): &Input) -> u32 {
         }
         visited.insert(curr_position_with_direction);
 
-        handle_move_forwards(&mut priority_queue, &curr_state, *end, walls);
-        handle_rotate(&mut priority_queue, &curr_state);
+        handle_move_forwards(
+            &mut priority_queue,
+            &curr_state,
+            input,
+            costs,
+            cheapest_step,
+        );
+        handle_rotate(&mut priority_queue, &curr_state, costs);
     }
 
     panic!("No solution found!");
 }
 
-fn part2((start, end, walls): &Input) -> u32 {
+fn distances(
+    (_, _, walls, tile_costs): &Input,
+    costs: &CostModel,
+    sources: &[PositionWithDirection],
+    backwards: bool,
+) -> Distances {
     let mut priority_queue = BinaryHeap::new();
-    let mut visited = HashMap::<PositionWithDirection, u32>::new();
-    let mut min_score = None;
-    let mut best_tiles = HashSet::new();
+    let mut distances = HashMap::new();
 
-    let start_state = State {
-        position_with_direction: PositionWithDirection::from_position_and_direction(
+    for source in sources {
+        priority_queue.push(State {
+            position_with_direction: *source,
+            score: 0,
+            estimated_distance: 0,
+        });
+    }
+
+    while let Some(curr_state) = priority_queue.pop() {
+        if distances.contains_key(&curr_state.position_with_direction) {
+            continue;
+        }
+        distances.insert(curr_state.position_with_direction, curr_state.score);
+
+        let curr_position = curr_state.position_with_direction.get_position();
+        let curr_direction = curr_state.position_with_direction.get_direction();
+
+        // Walking backwards reverses every edge: we come from the tile behind
+        // us, paying the cost of the tile we are standing on. Turns are
+        // symmetric, so they stay the same in both directions.
+        let (next_step, step_cost) = if backwards {
+            let previous_step = next_position(curr_position, opposite(curr_direction));
+            (previous_step, costs.step_cost(curr_position, tile_costs))
+        } else {
+            let next_step = next_position(curr_position, curr_direction);
+            (next_step, costs.step_cost(next_step, tile_costs))
+        };
+        if !walls.contains(&next_step) {
+            priority_queue.push(State {
+                position_with_direction: PositionWithDirection::from_position_and_direction(
+                    next_step,
+                    curr_direction,
+                ),
+                score: curr_state.score + step_cost,
+                estimated_distance: 0,
+            });
+        }
+
+        for (next_direction, cost) in rotations(curr_direction, costs) {
+            priority_queue.push(State {
+                position_with_direction: PositionWithDirection::from_position_and_direction(
+                    curr_position,
+                    next_direction,
+                ),
+                score: curr_state.score + cost,
+                estimated_distance: 0,
+            });
+        }
+    }
+
+    distances
+}
+
+fn best_path_distances(input: &Input, costs: &CostModel) -> BestPaths {
+    let (start, end, _, _) = input;
+    let end_states: Vec<PositionWithDirection> = [
+        Direction::NORTH,
+        Direction::EAST,
+        Direction::SOUTH,
+        Direction::WEST,
+    ]
+    .into_iter()
+    .map(|direction| PositionWithDirection::from_position_and_direction(*end, direction))
+    .collect();
+
+    let forward = distances(
+        input,
+        costs,
+        &[PositionWithDirection::from_position_and_direction(
             *start,
-            Direction::EAST,
-        ),
-        score: 0,
-        estimated_distance: manhattan(*start, *end),
-        path: HashSet::new(),
+            costs.start_direction,
+        )],
+        false,
+    );
+    let backward = distances(input, costs, &end_states, true);
+
+    let best = end_states
+        .iter()
+        .filter_map(|end_state| forward.get(end_state))
+        .min()
+        .copied()
+        .expect("No solution found!");
+
+    (forward, backward, best)
+}
+
+fn best_path_tiles((forward, backward, best): &BestPaths) -> Vec<Position> {
+    let mut best_tiles: Vec<Position> = forward
+        .iter()
+        .filter(|(state, score)| {
+            backward
+                .get(state)
+                .is_some_and(|rest| *score + rest == *best)
+        })
+        .map(|(state, _)| state.get_position())
+        .collect::<HashSet<Position>>()
+        .into_iter()
+        .collect();
+    best_tiles.sort_unstable_by_key(|tile| (tile.get_row(), tile.get_col()));
+    best_tiles
+}
+
+fn optimal_route(
+    input: &Input,
+    costs: &CostModel,
+    (forward, backward, best): &BestPaths,
+) -> Vec<PositionWithDirection> {
+    let (start, end, walls, tile_costs) = input;
+    let on_best_path = |state: &PositionWithDirection| {
+        forward.get(state).is_some_and(|score| {
+            backward
+                .get(state)
+                .is_some_and(|rest| score + rest == *best)
+        })
     };
-    priority_queue.push(start_state);
 
-    while !priority_queue.is_empty() {
-        let curr_state = priority_queue.pop().unwrap();
-        let curr_position = curr_state.position_with_direction.get_position();
+    let mut curr_state =
+        PositionWithDirection::from_position_and_direction(*start, costs.start_direction);
+    let mut route = vec![curr_state];
+    let mut visited = HashSet::from([curr_state]);
+
+    while curr_state.get_position() != *end {
+        let curr_position = curr_state.get_position();
+        let curr_direction = curr_state.get_direction();
 
-        if min_score.is_some() && curr_state.score > min_score.unwrap() {
-            break;
+        let next_step = next_position(curr_position, curr_direction);
+        let mut candidates = Vec::new();
+        if !walls.contains(&next_step) {
+            candidates.push((
+                PositionWithDirection::from_position_and_direction(next_step, curr_direction),
+                costs.step_cost(next_step, tile_costs),
+            ));
         }
-        if curr_position == *end {
-            min_score = Some(curr_state.score);
-            for tile in curr_state.path {
-                best_tiles.insert(tile);
-            }
-            best_tiles.insert(*end);
-            continue;
+        for (next_direction, cost) in rotations(curr_direction, costs) {
+            candidates.push((
+                PositionWithDirection::from_position_and_direction(curr_position, next_direction),
+                cost,
+            ));
         }
 
-        match visited.get(&curr_state.position_with_direction) {
-            Some(prev_score) => {
-                if *prev_score < curr_state.score {
-                    continue;
-                }
-            }
-            None => {
-                visited.insert(curr_state.position_with_direction, curr_state.score);
-            }
+        // Prefer moving forwards, and never revisit a state so that free
+        // turns cannot trap us in a loop.
+        curr_state = candidates
+            .into_iter()
+            .find(|(next_state, cost)| {
+                !visited.contains(next_state)
+                    && on_best_path(next_state)
+                    && forward[&curr_state] + cost == forward[next_state]
+            })
+            .map(|(next_state, _)| next_state)
+            .expect("Optimal route interrupted!");
+        route.push(curr_state);
+        visited.insert(curr_state);
+    }
+
+    route
+}
+
+fn turn_points(route: &[PositionWithDirection]) -> Vec<Position> {
+    let mut turn_points: Vec<Position> = Vec::new();
+
+    for (curr_state, next_state) in route.iter().zip(route.iter().skip(1)) {
+        let position = curr_state.get_position();
+        if next_state.get_position() == position && turn_points.last() != Some(&position) {
+            turn_points.push(position);
+        }
+    }
+
+    turn_points
+}
+
+fn render(input: &Input, costs: &CostModel, coloured: bool) -> String {
+    let (start, end, walls, tile_costs) = input;
+    let best_paths = best_path_distances(input, costs);
+    let route = optimal_route(input, costs, &best_paths);
+    let best_tiles: HashSet<Position> = best_path_tiles(&best_paths).into_iter().collect();
+
+    // Later states overwrite earlier ones, so each tile shows the direction
+    // in which the route leaves it.
+    let arrows: HashMap<Position, Direction> = route
+        .iter()
+        .map(|state| (state.get_position(), state.get_direction()))
+        .collect();
+
+    let all_positions = walls.iter().chain(tile_costs.keys()).chain([start, end]);
+    let max_row = all_positions.clone().map(|p| p.get_row()).max().unwrap();
+    let max_col = all_positions.map(|p| p.get_col()).max().unwrap();
+
+    let paint = |text: String, colour: &str| {
+        if coloured {
+            format!("\x1b[{colour}m{text}\x1b[0m")
+        } else {
+            text
         }
+    };
 
-        handle_move_forwards(&mut priority_queue, &curr_state, *end, walls);
-        handle_rotate(&mut priority_queue, &curr_state);
+    let mut output = String::new();
+    for r in 0..=max_row {
+        for c in 0..=max_col {
+            let position = Position::new(r, c);
+            let cell = if walls.contains(&position) {
+                paint(String::from("#"), "2")
+            } else if position == *start {
+                paint(String::from("S"), "1;36")
+            } else if position == *end {
+                paint(String::from("E"), "1;36")
+            } else if let Some(direction) = arrows.get(&position) {
+                let arrow = match direction {
+                    Direction::NORTH => "^",
+                    Direction::EAST => ">",
+                    Direction::SOUTH => "v",
+                    Direction::WEST => "<",
+                };
+                paint(String::from(arrow), "1;32")
+            } else if best_tiles.contains(&position) {
+                paint(String::from("O"), "33")
+            } else if let Some(cost) = tile_costs.get(&position) {
+                cost.to_string()
+            } else {
+                String::from(".")
+            };
+            output.push_str(&cell);
+        }
+        output.push('\n');
     }
 
-    u32::try_from(best_tiles.len()).expect("Invalid result!")
+    let turn_points: Vec<String> = turn_points(&route)
+        .iter()
+        .map(|position| format!("({}, {})", position.get_row(), position.get_col()))
+        .collect();
+    output.push_str(&format!("Turn points: {}\n", turn_points.join(", ")));
+
+    output
+}
+
+fn part1(input: &Input) -> u32 {
+    lowest_score(input, &CostModel::default())
+}
+
+fn part2(input: &Input) -> u32 {
+    u32::try_from(best_path_tiles(&best_path_distances(input, &CostModel::default())).len())
+        .expect("Invalid result!")
 }
 
 fn main() {
@@ -211,8 +473,53 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 64);
 
+    let weighted_input = parse_input(String::from("######\n#S99E#\n#.##.#\n#....#\n######\n"));
+    assert_eq!(part1(&weighted_input), 19);
+    let cheap_turns = CostModel {
+        turn_cost: 1,
+        ..CostModel::default()
+    };
+    assert_eq!(lowest_score(&weighted_input, &cheap_turns), 10);
+    let facing_west = CostModel {
+        u_turn_cost: Some(500),
+        start_direction: Direction::WEST,
+        ..CostModel::default()
+    };
+    assert_eq!(lowest_score(&weighted_input, &facing_west), 519);
+    assert_eq!(
+        best_path_tiles(&best_path_distances(&weighted_input, &cheap_turns)),
+        vec![
+            Position::new(1, 1),
+            Position::new(1, 4),
+            Position::new(2, 1),
+            Position::new(2, 4),
+            Position::new(3, 1),
+            Position::new(3, 2),
+            Position::new(3, 3),
+            Position::new(3, 4),
+        ]
+    );
+
+    assert_eq!(
+        render(&weighted_input, &cheap_turns, false),
+        "######\n#S99E#\n#v##^#\n#>>>^#\n######\nTurn points: (1, 1), (3, 1), (3, 4)\n"
+    );
+
     let text = helpers::input_file!();
     let input = parse_input(text);
+
+    match std::env::args().nth(1).as_deref() {
+        Some("render") => {
+            print!("{}", render(&input, &CostModel::default(), false));
+            return;
+        }
+        Some("render-ansi") => {
+            print!("{}", render(&input, &CostModel::default(), true));
+            return;
+        }
+        _ => {}
+    }
+
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
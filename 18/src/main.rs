@@ -1,24 +1,96 @@
 use helpers::position::Position;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+#[derive(Copy, Clone)]
+enum Axes {
+    // Coordinates are written as `x,y`: distance from the left edge first.
+    XY,
+    // Coordinates are written as `row,col`: distance from the top edge first.
+    RowCol,
+}
+
+impl Axes {
+    fn to_position(self, first: i32, second: i32) -> Position {
+        match self {
+            Axes::XY => Position::new(second, first),
+            Axes::RowCol => Position::new(first, second),
+        }
+    }
+
+    fn format(self, position: Position) -> String {
+        match self {
+            Axes::XY => format!("{},{}", position.get_col(), position.get_row()),
+            Axes::RowCol => format!("{},{}", position.get_row(), position.get_col()),
+        }
+    }
+}
+
+struct MemorySpace {
+    width: usize,
+    height: usize,
+    start: Position,
+    goal: Position,
+    axes: Axes,
+}
+
+impl MemorySpace {
+    fn square(size: usize) -> Self {
+        MemorySpace {
+            width: size,
+            height: size,
+            start: Position::new(0, 0),
+            goal: Position::from_usize(size - 1, size - 1),
+            axes: Axes::XY,
+        }
+    }
+
+    fn contains(&self, position: Position) -> bool {
+        position.is_within_bounds(self.height, self.width)
+    }
+
+    fn index_of(&self, position: Position) -> usize {
+        position.get_urow() * self.width + position.get_ucol()
+    }
+}
+
+fn parse_input(text: String, space: &MemorySpace) -> Vec<Position> {
+    for endpoint in [space.start, space.goal] {
+        if !space.contains(endpoint) {
+            panic!(
+                "Endpoint {} lies outside the {}x{} memory space",
+                space.axes.format(endpoint),
+                space.width,
+                space.height
+            );
+        }
+    }
 
-fn parse_input(text: String) -> Vec<Position> {
     text.lines()
         .map(|line| {
             let parts = line.split(',').collect::<Vec<&str>>();
-            Position::new(parts[0].parse().unwrap(), parts[1].parse().unwrap())
+            let position = space
+                .axes
+                .to_position(parts[0].parse().unwrap(), parts[1].parse().unwrap());
+            if !space.contains(position) {
+                panic!(
+                    "Byte {line} lies outside the {}x{} memory space",
+                    space.width, space.height
+                );
+            }
+            position
         })
         .collect()
 }
 
 #[allow(dead_code)]
-fn print_map(positions: &[Position], max_x: i32, max_y: i32) {
+fn print_map(positions: &[Position], space: &MemorySpace) {
     let positions_set = positions.iter().copied().collect::<HashSet<Position>>();
-    for y in 0..max_y {
-        for x in 0..max_x {
+    for row in 0..space.height {
+        for col in 0..space.width {
             print!(
                 "{}",
-                if positions_set.contains(&Position::new(x, y)) {
+                if positions_set.contains(&Position::from_usize(row, col)) {
                     '#'
                 } else {
                     '.'
@@ -51,22 +123,26 @@ impl Ord for State {
 }
 
 fn manhattan_distance(curr_position: Position, end_position: Position) -> usize {
-    end_position.get_urow() - curr_position.get_urow() + end_position.get_ucol()
-        - curr_position.get_ucol()
+    usize::try_from(
+        curr_position.get_row().abs_diff(end_position.get_row())
+            + curr_position.get_col().abs_diff(end_position.get_col()),
+    )
+    .expect("Invalid manhattan distance")
 }
 
 fn a_star(
     obstacles: &[Position],
     num_obstacles_to_consider: usize,
-    end_position: Position,
+    space: &MemorySpace,
 ) -> Option<usize> {
-    let max_rows = end_position.get_urow() + 1;
-    let max_cols = end_position.get_ucol() + 1;
-
-    let obstacles_to_consider = &obstacles[..num_obstacles_to_consider];
-    // print_map(obstacles_to_consider, max_rows as i32, max_cols as i32);
+    let obstacles_to_consider: HashSet<Position> = obstacles[..num_obstacles_to_consider]
+        .iter()
+        .copied()
+        .collect();
+    // print_map(&obstacles[..num_obstacles_to_consider], space);
 
-    let starting_position = Position::new(0, 0);
+    let starting_position = space.start;
+    let end_position = space.goal;
     let neighbours = [
         Position::new(-1, 0),
         Position::new(1, 0),
@@ -92,7 +168,7 @@ fn a_star(
 
         for direction in neighbours {
             let neighbour = curr_state.position + direction;
-            if !neighbour.is_within_bounds(max_rows, max_cols) {
+            if !space.contains(neighbour) {
                 continue;
             }
             if visited.contains(&neighbour) {
@@ -113,52 +189,344 @@ fn a_star(
     None
 }
 
-fn part1(
-    obstacles: &[Position],
-    num_obstacles_to_consider: usize,
-    end_position: Position,
-) -> usize {
-    match a_star(obstacles, num_obstacles_to_consider, end_position) {
+fn part1(obstacles: &[Position], num_obstacles_to_consider: usize, space: &MemorySpace) -> usize {
+    match a_star(obstacles, num_obstacles_to_consider, space) {
         Some(answer) => answer,
         None => panic!("No solution found"),
     }
 }
 
-fn part2(obstacles: &[Position], end_position: Position) -> String {
-    let mut lo = 0;
-    let mut hi = obstacles.len();
+struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind {
+            parents: (0..size).collect(),
+            ranks: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        let mut curr = element;
+        while self.parents[curr] != root {
+            let next = self.parents[curr];
+            self.parents[curr] = root;
+            curr = next;
+        }
+
+        root
+    }
+
+    fn union(&mut self, element1: usize, element2: usize) {
+        let root1 = self.find(element1);
+        let root2 = self.find(element2);
+        if root1 == root2 {
+            return;
+        }
+
+        match self.ranks[root1].cmp(&self.ranks[root2]) {
+            Ordering::Less => self.parents[root1] = root2,
+            Ordering::Greater => self.parents[root2] = root1,
+            Ordering::Equal => {
+                self.parents[root2] = root1;
+                self.ranks[root1] += 1;
+            }
+        }
+    }
+}
+
+fn first_fallen(obstacles: &[Position]) -> HashMap<Position, usize> {
+    let mut first_fallen = HashMap::new();
+    for (i, obstacle) in obstacles.iter().enumerate() {
+        first_fallen.entry(*obstacle).or_insert(i);
+    }
+    first_fallen
+}
+
+// The byte index is 0-based, matching the time step at which that byte lands
+// in the timed model below.
+fn first_blocking_byte(obstacles: &[Position], space: &MemorySpace) -> Option<(usize, Position)> {
+    // A cell only becomes free again once we rewind past the first byte that
+    // landed on it.
+    let first_fallen = first_fallen(obstacles);
+
+    let mut union_find = UnionFind::new(space.width * space.height);
+    let mut free_cells = HashSet::new();
+    let mut free_cell = |union_find: &mut UnionFind, position: Position| {
+        free_cells.insert(position);
+        for neighbour in [
+            position.up(),
+            position.down(),
+            position.left(),
+            position.right(),
+        ] {
+            if space.contains(neighbour) && free_cells.contains(&neighbour) {
+                union_find.union(space.index_of(position), space.index_of(neighbour));
+            }
+        }
+    };
+
+    for row in 0..space.height {
+        for col in 0..space.width {
+            let position = Position::from_usize(row, col);
+            if !first_fallen.contains_key(&position) {
+                free_cell(&mut union_find, position);
+            }
+        }
+    }
+
+    let start = space.index_of(space.start);
+    let end = space.index_of(space.goal);
+    if union_find.find(start) == union_find.find(end) {
+        return None;
+    }
+
+    for (i, obstacle) in obstacles.iter().enumerate().rev() {
+        if first_fallen[obstacle] != i {
+            continue;
+        }
+        free_cell(&mut union_find, *obstacle);
+        if union_find.find(start) == union_find.find(end) {
+            return Some((i, *obstacle));
+        }
+    }
+
+    None
+}
+
+fn part2(obstacles: &[Position], space: &MemorySpace) -> (usize, String) {
+    match first_blocking_byte(obstacles, space) {
+        Some((index, last_obstacle)) => (index, space.axes.format(last_obstacle)),
+        None => panic!("No solution found"),
+    }
+}
+
+// Byte `i` (0-based, as reported by `part2`) lands at time step `i`, so at
+// time `t` the bytes `0..=t` have fallen.
+fn is_free_at(first_fallen: &HashMap<Position, usize>, position: Position, time: usize) -> bool {
+    first_fallen
+        .get(&position)
+        .is_none_or(|&index| index > time)
+}
+
+fn last_landing(obstacles: &[Position]) -> usize {
+    obstacles.len().saturating_sub(1)
+}
+
+fn timed_moves(position: Position, waiting: bool) -> Vec<Position> {
+    let mut moves = vec![
+        position.up(),
+        position.down(),
+        position.left(),
+        position.right(),
+    ];
+    if waiting {
+        moves.push(position);
+    }
+    moves
+}
+
+fn timed_path(
+    obstacles: &[Position],
+    space: &MemorySpace,
+    departure: usize,
+    waiting: bool,
+) -> Option<Vec<Position>> {
+    let first_fallen = first_fallen(obstacles);
+    if !is_free_at(&first_fallen, space.start, departure) {
+        return None;
+    }
 
-    while lo < hi {
-        let mid = lo + (hi - lo) / 2;
-        match a_star(obstacles, mid, end_position) {
-            Some(_) => lo = mid,
-            None => match a_star(obstacles, mid - 1, end_position) {
-                Some(_) => {
-                    let last_obstacle = obstacles[mid - 1];
-                    return format!("{},{}", last_obstacle.get_row(), last_obstacle.get_col());
+    // One frontier per time step, each remembering where its cells came from.
+    let mut layers: Vec<HashMap<Position, Position>> =
+        vec![HashMap::from([(space.start, space.start)])];
+    // Once every byte has fallen the grid no longer changes, so revisiting a
+    // cell can only make the path longer.
+    let mut settled = HashSet::new();
+
+    loop {
+        let frontier = layers.last().unwrap();
+        if frontier.contains_key(&space.goal) {
+            break;
+        }
+
+        let time = departure + layers.len();
+        let mut next_frontier = HashMap::new();
+        for &position in frontier.keys() {
+            for next_position in timed_moves(position, waiting) {
+                if space.contains(next_position)
+                    && is_free_at(&first_fallen, next_position, time)
+                    && !settled.contains(&next_position)
+                {
+                    next_frontier.entry(next_position).or_insert(position);
                 }
-                None => hi = mid,
-            },
+            }
+        }
+
+        if time >= last_landing(obstacles) {
+            settled.extend(next_frontier.keys().copied());
+        }
+        if next_frontier.is_empty() {
+            return None;
+        }
+        layers.push(next_frontier);
+    }
+
+    let mut path = vec![space.goal];
+    for layer in layers.iter().rev().take(layers.len() - 1) {
+        path.push(layer[path.last().unwrap()]);
+    }
+    path.reverse();
+    Some(path)
+}
+
+// Returns the time the last byte lands when the exit is still reachable after
+// it, in which case any later departure works as well.
+fn latest_departure(obstacles: &[Position], space: &MemorySpace, waiting: bool) -> Option<usize> {
+    let first_fallen = first_fallen(obstacles);
+    let cells: Vec<Position> = (0..space.height)
+        .flat_map(|row| (0..space.width).map(move |col| Position::from_usize(row, col)))
+        .collect();
+
+    // Cells from which the exit can be reached once the grid stops changing.
+    let final_time = last_landing(obstacles);
+    let mut can_reach_exit = HashSet::new();
+    let mut queue = vec![space.goal];
+    if is_free_at(&first_fallen, space.goal, final_time) {
+        can_reach_exit.insert(space.goal);
+    } else {
+        queue.clear();
+    }
+    while let Some(position) = queue.pop() {
+        for neighbour in timed_moves(position, false) {
+            if space.contains(neighbour)
+                && is_free_at(&first_fallen, neighbour, final_time)
+                && can_reach_exit.insert(neighbour)
+            {
+                queue.push(neighbour);
+            }
         }
     }
+    if can_reach_exit.contains(&space.start) {
+        return Some(final_time);
+    }
 
-    panic!("No solution found");
+    // Walk back in time: a cell is good at time `t` if it is the exit, or if
+    // one move leads to a cell that is good at time `t + 1`.
+    for time in (0..final_time).rev() {
+        can_reach_exit = cells
+            .iter()
+            .copied()
+            .filter(|&position| {
+                is_free_at(&first_fallen, position, time)
+                    && (position == space.goal
+                        || timed_moves(position, waiting)
+                            .iter()
+                            .any(|next_position| can_reach_exit.contains(next_position)))
+            })
+            .collect();
+        if can_reach_exit.contains(&space.start) {
+            return Some(time);
+        }
+    }
+
+    None
 }
 
 fn main() {
     let sample_text = helpers::sample_file!();
-    let sample_input = parse_input(sample_text);
-    let end_position = Position::new(6, 6);
-    let sample_answer1 = part1(&sample_input, 12, end_position);
+    let sample_space = MemorySpace::square(7);
+    let sample_input = parse_input(sample_text.clone(), &sample_space);
+    let sample_answer1 = part1(&sample_input, 12, &sample_space);
     assert_eq!(sample_answer1, 22);
-    let sample_answer2 = part2(&sample_input, end_position);
-    assert_eq!(sample_answer2, "6,1");
+    let sample_answer2 = part2(&sample_input, &sample_space);
+    assert_eq!(sample_answer2, (20, String::from("6,1")));
+
+    let sample_path = timed_path(&sample_input, &sample_space, 0, false).unwrap();
+    assert_eq!(sample_path.len() - 1, 12);
+    assert_eq!(sample_path.first(), Some(&sample_space.start));
+    assert_eq!(sample_path.last(), Some(&sample_space.goal));
+    assert_eq!(
+        timed_path(&sample_input, &sample_space, 5, true).map(|path| path.len() - 1),
+        Some(22)
+    );
+    assert_eq!(timed_path(&sample_input, &sample_space, 10, true), None);
+    assert_eq!(
+        latest_departure(&sample_input, &sample_space, false),
+        Some(8)
+    );
+    assert_eq!(
+        latest_departure(&sample_input, &sample_space, true),
+        Some(8)
+    );
+
+    // Byte 0 lands at time 0, so it blocks the start before anyone can leave.
+    let corner_space = MemorySpace {
+        width: 3,
+        height: 2,
+        start: Position::new(0, 0),
+        goal: Position::new(0, 2),
+        axes: Axes::XY,
+    };
+    let blocked_start = parse_input(String::from("0,0\n0,1\n"), &corner_space);
+    assert_eq!(timed_path(&blocked_start, &corner_space, 0, true), None);
+    assert_eq!(latest_departure(&blocked_start, &corner_space, true), None);
+    // Byte 1 lands on (0,1) exactly when the walker would step onto it.
+    let closing_door = parse_input(String::from("0,1\n1,0\n"), &corner_space);
+    assert_eq!(timed_path(&closing_door, &corner_space, 0, false), None);
+    assert_eq!(
+        timed_path(
+            &parse_input(String::from("0,1\n2,1\n1,0\n"), &corner_space),
+            &corner_space,
+            0,
+            false
+        ),
+        Some(vec![
+            Position::new(0, 0),
+            Position::new(0, 1),
+            Position::new(0, 2)
+        ])
+    );
+
+    let reversed_space = MemorySpace {
+        start: sample_space.goal,
+        goal: sample_space.start,
+        ..MemorySpace::square(7)
+    };
+    assert_eq!(part1(&sample_input, 12, &reversed_space), 22);
+    let transposed_space = MemorySpace {
+        axes: Axes::RowCol,
+        ..MemorySpace::square(7)
+    };
+    let transposed_input = parse_input(sample_text, &transposed_space);
+    assert_eq!(
+        part2(&transposed_input, &transposed_space),
+        (20, String::from("6,1"))
+    );
+    let wide_space = MemorySpace {
+        width: 3,
+        height: 2,
+        start: Position::new(0, 0),
+        goal: Position::new(1, 2),
+        axes: Axes::XY,
+    };
+    let wide_input = parse_input(String::from("1,0\n1,1\n"), &wide_space);
+    assert_eq!(part1(&wide_input, 1, &wide_space), 3);
+    assert_eq!(part2(&wide_input, &wide_space), (1, String::from("1,1")));
 
     let text = helpers::input_file!();
-    let input = parse_input(text);
-    let end_position = Position::new(70, 70);
-    let answer1 = part1(&input, 1024, end_position);
+    let space = MemorySpace::square(71);
+    let input = parse_input(text, &space);
+    let answer1 = part1(&input, 1024, &space);
     println!("{answer1}");
-    let answer2 = part2(&input, end_position);
-    println!("{answer2}");
+    let (index2, answer2) = part2(&input, &space);
+    println!("{answer2} (byte {index2})");
 }
@@ -10,45 +10,286 @@ fn parse_input(text: String) -> Input {
     (patterns, desired_designs)
 }
 
-fn count_ways<'a>(
-    design: &'a str,
-    patterns: &HashSet<String>,
-    memo: &mut HashMap<&'a str, u64>,
-) -> u64 {
-    if memo.contains_key(design) {
-        return memo[design];
+#[derive(Default)]
+struct TrieNode {
+    children: HashMap<u8, usize>,
+    is_pattern: bool,
+}
+
+struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+impl Trie {
+    fn new<'a>(patterns: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut trie = Trie {
+            nodes: vec![TrieNode::default()],
+        };
+
+        for pattern in patterns {
+            let mut node = 0;
+            for byte in pattern.bytes() {
+                node = match trie.nodes[node].children.get(&byte) {
+                    Some(&child) => child,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.insert(byte, child);
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].is_pattern = true;
+        }
+
+        trie
     }
 
-    if design.is_empty() {
-        return 1;
+    // Lengths of all the patterns that `bytes` starts with, shortest first.
+    fn prefix_lengths<'t>(&'t self, bytes: &'t [u8]) -> impl Iterator<Item = usize> + 't {
+        bytes
+            .iter()
+            .scan(0, |node, byte| {
+                *node = *self.nodes[*node].children.get(byte)?;
+                Some(*node)
+            })
+            .enumerate()
+            .filter(|&(_, node)| self.nodes[node].is_pattern)
+            .map(|(i, _)| i + 1)
     }
 
-    let mut num_ways = 0;
-    for pattern in patterns {
-        if design.starts_with(pattern) {
-            num_ways += count_ways(&design[pattern.len()..], patterns, memo);
+    // Number of bytes that can be followed in the trie before falling off it.
+    fn match_depth(&self, bytes: &[u8]) -> usize {
+        let mut node = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            match self.nodes[node].children.get(byte) {
+                Some(&child) => node = child,
+                None => return i,
+            }
         }
+        bytes.len()
     }
+}
+
+fn count_ways(design: &str, trie: &Trie) -> u128 {
+    let bytes = design.as_bytes();
+
+    // ways[i] is the number of ways to build the suffix starting at byte i.
+    let mut ways = vec![0u128; bytes.len() + 1];
+    ways[bytes.len()] = 1;
+    for i in (0..bytes.len()).rev() {
+        ways[i] = trie
+            .prefix_lengths(&bytes[i..])
+            .try_fold(0u128, |total, length| total.checked_add(ways[i + length]))
+            .expect("Too many ways to build the design");
+    }
+
+    ways[0]
+}
+
+fn can_finish(bytes: &[u8], trie: &Trie) -> Vec<bool> {
+    let mut can_finish = vec![false; bytes.len() + 1];
+    can_finish[bytes.len()] = true;
+    for i in (0..bytes.len()).rev() {
+        can_finish[i] = trie
+            .prefix_lengths(&bytes[i..])
+            .any(|length| can_finish[i + length]);
+    }
+    can_finish
+}
 
-    memo.insert(design, num_ways);
-    num_ways
+struct Decompositions<'d, 't> {
+    design: &'d str,
+    trie: &'t Trie,
+    can_finish: Vec<bool>,
+    towels: Vec<&'d str>,
+    stack: Vec<(usize, Vec<usize>, usize)>,
+}
+
+impl<'d> Decompositions<'d, '_> {
+    // Only keep the towels after which the rest of the design can still be built.
+    fn towel_lengths(&self, start: usize) -> Vec<usize> {
+        self.trie
+            .prefix_lengths(&self.design.as_bytes()[start..])
+            .filter(|length| self.can_finish[start + length])
+            .collect()
+    }
+}
+
+impl<'d> Iterator for Decompositions<'d, '_> {
+    type Item = Vec<&'d str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((start, lengths, index)) = self.stack.last_mut() {
+            if *start == self.design.len() && *index == 0 {
+                *index = 1;
+                return Some(self.towels.clone());
+            }
+            if *index >= lengths.len() {
+                self.stack.pop();
+                self.towels.pop();
+                continue;
+            }
+
+            let end = *start + lengths[*index];
+            self.towels.push(&self.design[*start..end]);
+            *index += 1;
+
+            let next_lengths = self.towel_lengths(end);
+            self.stack.push((end, next_lengths, 0));
+        }
+
+        None
+    }
+}
+
+fn decompositions<'d, 't>(design: &'d str, trie: &'t Trie) -> Decompositions<'d, 't> {
+    let mut decompositions = Decompositions {
+        design,
+        trie,
+        can_finish: can_finish(design.as_bytes(), trie),
+        towels: Vec::new(),
+        stack: Vec::new(),
+    };
+    if decompositions.can_finish[0] {
+        let lengths = decompositions.towel_lengths(0);
+        decompositions.stack.push((0, lengths, 0));
+    }
+    decompositions
+}
+
+fn extreme_decomposition<'d>(
+    design: &'d str,
+    trie: &Trie,
+    is_better: fn(usize, usize) -> bool,
+) -> Option<Vec<&'d str>> {
+    let bytes = design.as_bytes();
+
+    // best[i] holds the towel count and first towel length of the best way to
+    // build the suffix starting at byte i.
+    let mut best: Vec<Option<(usize, usize)>> = vec![None; bytes.len() + 1];
+    best[bytes.len()] = Some((0, 0));
+    for i in (0..bytes.len()).rev() {
+        for length in trie.prefix_lengths(&bytes[i..]) {
+            if let Some((count, _)) = best[i + length] {
+                if best[i].is_none_or(|(best_count, _)| is_better(count + 1, best_count)) {
+                    best[i] = Some((count + 1, length));
+                }
+            }
+        }
+    }
+
+    best[0]?;
+    let mut towels = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let (_, length) = best[start].unwrap();
+        towels.push(&design[start..start + length]);
+        start += length;
+    }
+    Some(towels)
+}
+
+fn fewest_towels<'d>(design: &'d str, trie: &Trie) -> Option<Vec<&'d str>> {
+    extreme_decomposition(design, trie, |count, best_count| count < best_count)
+}
+
+fn most_towels<'d>(design: &'d str, trie: &Trie) -> Option<Vec<&'d str>> {
+    extreme_decomposition(design, trie, |count, best_count| count > best_count)
+}
+
+// For a design that cannot be built, returns its longest buildable prefix and
+// the furthest byte at which every attempt to match a towel gave up.
+fn explain_failure<'d>(design: &'d str, trie: &Trie) -> Option<(&'d str, usize)> {
+    let bytes = design.as_bytes();
+
+    let mut reachable = vec![false; bytes.len() + 1];
+    reachable[0] = true;
+    let mut longest_prefix = 0;
+    let mut failed_at = 0;
+    for i in 0..bytes.len() {
+        if !reachable[i] {
+            continue;
+        }
+        longest_prefix = i;
+        failed_at = failed_at.max(i + trie.match_depth(&bytes[i..]));
+        for length in trie.prefix_lengths(&bytes[i..]) {
+            reachable[i + length] = true;
+        }
+    }
+
+    if reachable[bytes.len()] {
+        return None;
+    }
+    Some((&design[..longest_prefix], failed_at))
+}
+
+// A pattern is redundant when it can also be built from other, shorter towels.
+fn redundant_patterns(patterns: &HashSet<String>) -> Vec<String> {
+    let trie = Trie::new(patterns);
+    let mut redundant: Vec<String> = patterns
+        .iter()
+        .filter(|pattern| count_ways(pattern, &trie) > 1)
+        .cloned()
+        .collect();
+    redundant.sort_unstable();
+    redundant
+}
+
+// Returns a subset of patterns from which no towel can be dropped without
+// making one of the currently buildable designs impossible.
+fn minimal_patterns((patterns, desired_designs): &Input) -> Vec<String> {
+    let trie = Trie::new(patterns);
+    let buildable_designs: Vec<&String> = desired_designs
+        .iter()
+        .filter(|design| count_ways(design, &trie) > 0)
+        .collect();
+
+    // Redundant patterns can always be replaced by the towels they are made
+    // of, so they can all be dropped at once.
+    let redundant = redundant_patterns(patterns);
+    let mut kept: Vec<String> = patterns
+        .iter()
+        .filter(|pattern| redundant.binary_search(pattern).is_err())
+        .cloned()
+        .collect();
+    kept.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    let mut i = 0;
+    while i < kept.len() {
+        let others = Trie::new(kept.iter().take(i).chain(kept.iter().skip(i + 1)));
+        if buildable_designs
+            .iter()
+            .all(|design| count_ways(design, &others) > 0)
+        {
+            kept.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    kept.sort_unstable();
+    kept
 }
 
 fn part1((patterns, desired_designs): &Input) -> u64 {
+    let trie = Trie::new(patterns);
     u64::try_from(
         desired_designs
             .iter()
-            .filter(|design| count_ways(design, patterns, &mut HashMap::new()) > 0)
+            .filter(|design| count_ways(design, &trie) > 0)
             .count(),
     )
     .expect("Invalid count")
 }
 
-fn part2((patterns, desired_designs): &Input) -> u64 {
+fn part2((patterns, desired_designs): &Input) -> u128 {
+    let trie = Trie::new(patterns);
     desired_designs
         .iter()
-        .map(|design| count_ways(design, patterns, &mut HashMap::new()))
-        .sum()
+        .map(|design| count_ways(design, &trie))
+        .try_fold(0u128, u128::checked_add)
+        .expect("Too many ways to build the designs")
 }
 
 fn main() {
@@ -59,6 +300,37 @@ fn main() {
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, 16);
 
+    let sample_trie = Trie::new(&sample_input.0);
+    assert_eq!(
+        decompositions("brwrr", &sample_trie).collect::<Vec<_>>(),
+        vec![vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]]
+    );
+    assert_eq!(
+        fewest_towels("brwrr", &sample_trie),
+        Some(vec!["br", "wr", "r"])
+    );
+    assert_eq!(
+        most_towels("brwrr", &sample_trie),
+        Some(vec!["b", "r", "wr", "r"])
+    );
+    assert_eq!(explain_failure("brwrr", &sample_trie), None);
+    assert_eq!(explain_failure("ubwu", &sample_trie), Some(("", 0)));
+    assert_eq!(explain_failure("bbrgwb", &sample_trie), Some(("bbrg", 5)));
+    assert_eq!(redundant_patterns(&sample_input.0), vec!["br", "gb", "rb"]);
+    assert_eq!(
+        minimal_patterns(&sample_input),
+        vec!["b", "bwu", "g", "r", "wr"]
+    );
+    let unused_input = parse_input(String::from("a, b, ab, x, yz\n\naab\nba\nq\n"));
+    assert_eq!(redundant_patterns(&unused_input.0), vec!["ab"]);
+    assert_eq!(minimal_patterns(&unused_input), vec!["a", "b"]);
+    for design in &sample_input.1 {
+        assert_eq!(
+            decompositions(design, &sample_trie).count() as u128,
+            count_ways(design, &sample_trie)
+        );
+    }
+
     let text = helpers::input_file!();
     let input = parse_input(text);
     let answer1 = part1(&input);
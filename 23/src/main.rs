@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 
 type VertexSet<'a> = HashSet<&'a str>;
 type Graph<'a> = HashMap<&'a str, VertexSet<'a>>;
@@ -21,25 +22,80 @@ fn parse_input<'a>(text: &'a str) -> Graph<'a> {
     graph
 }
 
-fn part1(graph: &Graph) -> u32 {
-    let mut result = 0;
+struct KCliques<'g, 'a, F> {
+    graph: &'g Graph<'a>,
+    k: usize,
+    filter: F,
+    clique: Vec<&'a str>,
+    stack: Vec<(Vec<&'a str>, usize)>,
+}
+
+impl<'a, F: Fn(&str) -> bool> Iterator for KCliques<'_, 'a, F> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Each stack frame holds the sorted candidates that extend the current
+        // clique, so cliques come out in lexicographic order.
+        while let Some((candidates, index)) = self.stack.last_mut() {
+            if *index >= candidates.len() {
+                self.stack.pop();
+                self.clique.pop();
+                continue;
+            }
+
+            let v = candidates[*index];
+            *index += 1;
 
-    for v1 in graph.keys() {
-        for v2 in &graph[v1] {
-            for v3 in &graph[v2] {
-                if graph[v1].contains(v3)
-                    && graph[v2].contains(v3)
-                    && v3 > v2
-                    && v2 > v1
-                    && (v1.starts_with('t') || v2.starts_with('t') || v3.starts_with('t'))
-                {
-                    result += 1;
+            if self.clique.len() + 1 == self.k {
+                if self.clique.iter().any(|u| (self.filter)(u)) || (self.filter)(v) {
+                    let mut clique = self.clique.clone();
+                    clique.push(v);
+                    return Some(clique);
                 }
+                continue;
+            }
+
+            let next_candidates: Vec<&'a str> = candidates[*index..]
+                .iter()
+                .copied()
+                .filter(|u| self.graph[v].contains(u))
+                .collect();
+            if self.clique.len() + 1 + next_candidates.len() >= self.k {
+                self.clique.push(v);
+                self.stack.push((next_candidates, 0));
             }
         }
+
+        None
     }
+}
 
-    result
+fn enumerate_k_cliques<'g, 'a, F: Fn(&str) -> bool>(
+    graph: &'g Graph<'a>,
+    k: usize,
+    filter: F,
+) -> KCliques<'g, 'a, F> {
+    let vertices = sorted_vertices(graph);
+
+    KCliques {
+        graph,
+        k,
+        filter,
+        clique: Vec::new(),
+        stack: if k > 0 {
+            vec![(vertices, 0)]
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+fn count_k_cliques<F: Fn(&str) -> bool>(graph: &Graph, k: usize, filter: F) -> usize {
+    enumerate_k_cliques(graph, k, filter).count()
+}
+
+fn part1(graph: &Graph) -> usize {
+    count_k_cliques(graph, 3, |vertex| vertex.starts_with('t'))
 }
 
 fn bron_kerbosch<'a>(
@@ -73,37 +129,256 @@ fn bron_kerbosch<'a>(
     cliques
 }
 
-fn find_largest_clique<'a>(graph: &Graph<'a>) -> VertexSet<'a> {
+fn all_maximum_cliques<'a>(graph: &Graph<'a>) -> Vec<Vec<&'a str>> {
     let mut r = HashSet::new();
     let mut p = graph.keys().copied().collect();
     let mut x = HashSet::new();
 
     let cliques = bron_kerbosch(graph, &mut r, &mut p, &mut x);
-    cliques
-        .iter()
-        .max_by_key(|vertex_set| vertex_set.len())
-        .unwrap()
-        .clone()
+    let max_len = cliques.iter().map(HashSet::len).max().unwrap_or(0);
+
+    let mut maximum_cliques: Vec<Vec<&'a str>> = cliques
+        .into_iter()
+        .filter(|vertex_set| vertex_set.len() == max_len)
+        .map(|vertex_set| {
+            let mut sorted_keys: Vec<&'a str> = vertex_set.into_iter().collect();
+            sorted_keys.sort_unstable();
+            sorted_keys
+        })
+        .collect();
+    maximum_cliques.sort_unstable();
+    maximum_cliques
+}
+
+fn branch_and_bound<'a>(
+    graph: &Graph<'a>,
+    clique: &mut Vec<&'a str>,
+    candidates: &[&'a str],
+    best: &mut Vec<&'a str>,
+) {
+    if clique.len() > best.len() {
+        best.clone_from(clique);
+    }
+
+    // Candidates are visited in sorted order, so the first clique found with a
+    // given size is the lexicographically smallest one: only strictly larger
+    // cliques are worth exploring afterwards.
+    for (i, &v) in candidates.iter().enumerate() {
+        if clique.len() + candidates.len() - i <= best.len() {
+            return;
+        }
+
+        let next_candidates: Vec<&'a str> = candidates[i + 1..]
+            .iter()
+            .copied()
+            .filter(|u| graph[v].contains(u))
+            .collect();
+
+        clique.push(v);
+        branch_and_bound(graph, clique, &next_candidates, best);
+        clique.pop();
+    }
+}
+
+#[derive(Copy, Clone)]
+enum CliqueSearch {
+    Enumerate,
+    BranchAndBound,
+}
+
+fn find_largest_clique<'a>(graph: &Graph<'a>, search: CliqueSearch) -> VertexSet<'a> {
+    match search {
+        CliqueSearch::Enumerate => all_maximum_cliques(graph)
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+        CliqueSearch::BranchAndBound => {
+            let mut best = Vec::new();
+            branch_and_bound(graph, &mut Vec::new(), &sorted_vertices(graph), &mut best);
+            best.into_iter().collect()
+        }
+    }
 }
 
 fn part2<'a>(graph: &Graph<'a>) -> String {
-    let largest_clique = find_largest_clique(graph);
+    let largest_clique = find_largest_clique(graph, CliqueSearch::BranchAndBound);
 
     let mut sorted_keys: Vec<&'a str> = largest_clique.iter().copied().collect();
     sorted_keys.sort_unstable();
     sorted_keys.join(",")
 }
 
+fn sorted_vertices<'a>(graph: &Graph<'a>) -> Vec<&'a str> {
+    let mut vertices: Vec<&'a str> = graph.keys().copied().collect();
+    vertices.sort_unstable();
+    vertices
+}
+
+fn sorted_edges<'a>(graph: &Graph<'a>) -> Vec<(&'a str, &'a str)> {
+    let mut edges: Vec<(&'a str, &'a str)> = graph
+        .iter()
+        .flat_map(|(&v1, neighbours)| {
+            neighbours
+                .iter()
+                .filter(move |&&v2| v1 < v2)
+                .map(move |&v2| (v1, v2))
+        })
+        .collect();
+    edges.sort_unstable();
+    edges
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_dot(graph: &Graph, highlighted: Option<&VertexSet>) -> String {
+    let is_highlighted = |v: &str| highlighted.is_some_and(|set| set.contains(v));
+    let mut dot = String::from("graph {\n");
+
+    for v in sorted_vertices(graph) {
+        let name = escape_dot(v);
+        if is_highlighted(v) {
+            writeln!(dot, "    \"{name}\" [style=filled, fillcolor=red];").unwrap();
+        } else {
+            writeln!(dot, "    \"{name}\";").unwrap();
+        }
+    }
+
+    for (v1, v2) in sorted_edges(graph) {
+        let (name1, name2) = (escape_dot(v1), escape_dot(v2));
+        if is_highlighted(v1) && is_highlighted(v2) {
+            writeln!(
+                dot,
+                "    \"{name1}\" -- \"{name2}\" [color=red, penwidth=2];"
+            )
+            .unwrap();
+        } else {
+            writeln!(dot, "    \"{name1}\" -- \"{name2}\";").unwrap();
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn to_graphml(graph: &Graph, highlighted: Option<&VertexSet>) -> String {
+    let is_highlighted = |v: &str| highlighted.is_some_and(|set| set.contains(v));
+    let mut graphml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"highlighted\" for=\"all\" attr.name=\"highlighted\" attr.type=\"boolean\">\n",
+        "    <default>false</default>\n",
+        "  </key>\n",
+        "  <graph id=\"G\" edgedefault=\"undirected\">\n",
+    ));
+
+    for v in sorted_vertices(graph) {
+        let id = escape_xml(v);
+        if is_highlighted(v) {
+            writeln!(
+                graphml,
+                "    <node id=\"{id}\"><data key=\"highlighted\">true</data></node>"
+            )
+            .unwrap();
+        } else {
+            writeln!(graphml, "    <node id=\"{id}\"/>").unwrap();
+        }
+    }
+
+    for (v1, v2) in sorted_edges(graph) {
+        let (source, target) = (escape_xml(v1), escape_xml(v2));
+        if is_highlighted(v1) && is_highlighted(v2) {
+            writeln!(
+                graphml,
+                "    <edge source=\"{source}\" target=\"{target}\"><data key=\"highlighted\">true</data></edge>"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                graphml,
+                "    <edge source=\"{source}\" target=\"{target}\"/>"
+            )
+            .unwrap();
+        }
+    }
+
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
 fn main() {
     let sample_text = helpers::sample_file!();
     let sample_input = parse_input(&sample_text);
     let sample_answer1 = part1(&sample_input);
     assert_eq!(sample_answer1, 7);
+    assert_eq!(count_k_cliques(&sample_input, 3, |_| true), 12);
+    assert_eq!(
+        enumerate_k_cliques(&sample_input, 4, |vertex| vertex == "ka").collect::<Vec<_>>(),
+        vec![vec!["co", "de", "ka", "ta"]]
+    );
     let sample_answer2 = part2(&sample_input);
     assert_eq!(sample_answer2, "co,de,ka,ta");
 
+    assert_eq!(
+        all_maximum_cliques(&sample_input),
+        vec![vec!["co", "de", "ka", "ta"]]
+    );
+    let tied_graph = parse_input("kh-tc\nde-cg\nab-cd");
+    assert_eq!(
+        all_maximum_cliques(&tied_graph),
+        vec![vec!["ab", "cd"], vec!["cg", "de"], vec!["kh", "tc"]]
+    );
+    for search in [CliqueSearch::Enumerate, CliqueSearch::BranchAndBound] {
+        assert_eq!(
+            find_largest_clique(&tied_graph, search),
+            HashSet::from(["ab", "cd"])
+        );
+    }
+
+    let sample_clique = find_largest_clique(&sample_input, CliqueSearch::Enumerate);
+    let sample_dot = to_dot(&sample_input, Some(&sample_clique));
+    assert!(sample_dot.contains("    \"co\" [style=filled, fillcolor=red];\n"));
+    assert!(sample_dot.contains("    \"aq\" -- \"cg\";\n"));
+    let sample_graphml = to_graphml(&sample_input, None);
+    assert!(sample_graphml.contains("    <edge source=\"ka\" target=\"ta\"/>\n"));
+
     let text = helpers::input_file!();
     let input = parse_input(&text);
+
+    match std::env::args().nth(1).as_deref() {
+        Some("dot") => {
+            print!(
+                "{}",
+                to_dot(
+                    &input,
+                    Some(&find_largest_clique(&input, CliqueSearch::BranchAndBound))
+                )
+            );
+            return;
+        }
+        Some("graphml") => {
+            print!(
+                "{}",
+                to_graphml(
+                    &input,
+                    Some(&find_largest_clique(&input, CliqueSearch::BranchAndBound))
+                )
+            );
+            return;
+        }
+        _ => {}
+    }
+
     let answer1 = part1(&input);
     println!("{answer1}");
     let answer2 = part2(&input);
@@ -27,6 +27,6 @@ impl PositionWithDirection {
     }
 
     pub fn get_direction(&self) -> Direction {
-        self.direction.clone()
+        self.direction
     }
 }